@@ -0,0 +1,158 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Unsigned integer of arbitrary size; model and coloring counts overflow
+/// machine words quickly.
+///
+/// graph_coloring compiles this same file as its own `bignum` module.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BigNum(Vec<u32>);
+
+impl BigNum {
+    pub fn from_u64(num: u64) -> BigNum {
+        let mut big = BigNum(vec![num as u32, (num >> 32) as u32]);
+        big.trim();
+        big
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn add(&self, other: &BigNum) -> BigNum {
+        let len = self.0.len().max(other.0.len());
+        let mut digits = Vec::with_capacity(len + 1);
+        let mut carry = 0u64;
+        for i in 0..len {
+            let sum = *self.0.get(i).unwrap_or(&0) as u64
+                + *other.0.get(i).unwrap_or(&0) as u64
+                + carry;
+            digits.push(sum as u32);
+            carry = sum >> 32;
+        }
+        digits.push(carry as u32);
+        let mut big = BigNum(digits);
+        big.trim();
+        big
+    }
+
+    pub fn mul(&self, other: &BigNum) -> BigNum {
+        if self.is_zero() || other.is_zero() {
+            return BigNum(Vec::new());
+        }
+        let mut digits = vec![0u32; self.0.len() + other.0.len()];
+        for (i, &a) in self.0.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.0.iter().enumerate() {
+                let cur = digits[i + j] as u64 + a as u64 * b as u64 + carry;
+                digits[i + j] = cur as u32;
+                carry = cur >> 32;
+            }
+            digits[i + other.0.len()] = carry as u32;
+        }
+        let mut big = BigNum(digits);
+        big.trim();
+        big
+    }
+
+    /// The difference, which must not be negative.
+    pub fn sub(&self, other: &BigNum) -> BigNum {
+        let mut digits = Vec::with_capacity(self.0.len());
+        let mut borrow = 0i64;
+        for i in 0..self.0.len() {
+            let mut diff = self.0[i] as i64 - *other.0.get(i).unwrap_or(&0) as i64 - borrow;
+            borrow = 0;
+            if diff < 0 {
+                diff += 1 << 32;
+                borrow = 1;
+            }
+            digits.push(diff as u32);
+        }
+        assert!(borrow == 0 && other.0.len() <= self.0.len(), "BigNum subtraction underflow");
+        let mut big = BigNum(digits);
+        big.trim();
+        big
+    }
+
+    /// Multiplies by 2^bits.
+    pub fn shl(&self, bits: usize) -> BigNum {
+        if self.is_zero() {
+            return self.clone();
+        }
+        let mut digits = vec![0u32; bits / 32];
+        let shift = bits % 32;
+        let mut carry = 0u32;
+        for &digit in self.0.iter() {
+            if shift == 0 {
+                digits.push(digit);
+            } else {
+                digits.push(digit << shift | carry);
+                carry = digit >> (32 - shift);
+            }
+        }
+        digits.push(carry);
+        let mut big = BigNum(digits);
+        big.trim();
+        big
+    }
+
+    fn trim(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+}
+
+impl Display for BigNum {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+        // Peel off base 10^9 chunks, least significant first.
+        let mut digits = self.0.clone();
+        let mut chunks = Vec::new();
+        while !digits.is_empty() {
+            let mut rem = 0u64;
+            for digit in digits.iter_mut().rev() {
+                let cur = rem << 32 | *digit as u64;
+                *digit = (cur / 1_000_000_000) as u32;
+                rem = cur % 1_000_000_000;
+            }
+            chunks.push(rem);
+            while digits.last() == Some(&0) {
+                digits.pop();
+            }
+        }
+        let mut out = chunks.pop().unwrap().to_string();
+        while let Some(chunk) = chunks.pop() {
+            out.push_str(&format!("{:09}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BigNum;
+
+    #[test]
+    fn arithmetic_past_machine_words() {
+        let big = BigNum::from_u64(u64::MAX);
+        let one = BigNum::from_u64(1);
+        assert_eq!(big.add(&one).to_string(), "18446744073709551616");
+        assert_eq!(big.add(&one).sub(&one), big);
+        assert_eq!(big.mul(&big).to_string(), "340282366920938463426481119284349108225");
+        assert_eq!(big.mul(&big).sub(&big.mul(&big)), BigNum::from_u64(0));
+        assert_eq!(BigNum::from_u64(3).shl(70).to_string(), "3541774862152233910272");
+        assert_eq!(BigNum::from_u64(1).shl(64), big.add(&one));
+        assert!(BigNum::from_u64(0).shl(5).is_zero());
+        assert!(BigNum::from_u64(0).mul(&big).is_zero());
+        assert_eq!(BigNum::from_u64(1_000_000_000).to_string(), "1000000000");
+    }
+
+    #[test]
+    #[should_panic(expected = "underflow")]
+    fn subtraction_below_zero_panics() {
+        BigNum::from_u64(1).sub(&BigNum::from_u64(2));
+    }
+}
@@ -0,0 +1,376 @@
+extern crate cue6_05;
+
+use cue6_05::dimacs;
+use cue6_05::rng::Rng;
+use cue6_05::Lit;
+
+use std::env;
+use std::io::{self, Write};
+use std::process;
+
+/// A benchmark family with its size parameters.
+enum Family {
+    KSat { vars: usize, ratio: f64, k: usize },
+    Pigeonhole { pigeons: usize, holes: usize },
+    Coloring { vertices: usize, edges: usize, colors: usize },
+    Queens { n: usize },
+    Parity { vars: usize, equations: usize, k: usize },
+}
+
+fn usage() {
+    eprintln!("Usage: generate FAMILY ARGS [--seed N] [--xor] > FILE.cnf
+                      Families: ksat VARS RATIO [K]       Uniform random K-SAT, RATIO clauses per variable (K = 3)
+                                php PIGEONS [HOLES]       Pigeonhole principle (HOLES = PIGEONS - 1)
+                                color VERTICES EDGES K    K-coloring of a random graph
+                                queens N                  N non-attacking queens on an N by N board
+                                parity VARS EQUATIONS K   Random K-variable parity equations with a planted solution
+                      Options:  --seed N                  Seed of the generator (0 by default)
+                                --xor                     Write parity equations as x lines instead of clauses")
+}
+
+/// Reports an error and exits with failure. Standard error keeps the
+/// message out of the instance, which is usually redirected to a file.
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
+
+fn parse_args() -> Option<(Family, u64, bool)> {
+    let mut seed = 0;
+    let mut xor = false;
+    let mut words = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => seed = args.next()?.parse().ok()?,
+            "--xor" => xor = true,
+            _ => words.push(arg),
+        }
+    }
+
+    let num = |i: usize| words.get(i).and_then(|w| w.parse::<usize>().ok());
+    let family = match words.first().map(|w| w.as_str()) {
+        Some("ksat") => Family::KSat {
+            vars: num(1)?,
+            ratio: words.get(2)?.parse().ok()?,
+            k: if words.len() > 3 { num(3)? } else { 3 },
+        },
+        Some("php") => {
+            let pigeons = num(1)?;
+            let holes = if words.len() > 2 { num(2)? } else { pigeons.checked_sub(1)? };
+            Family::Pigeonhole { pigeons, holes }
+        },
+        Some("color") => Family::Coloring { vertices: num(1)?, edges: num(2)?, colors: num(3)? },
+        Some("queens") => Family::Queens { n: num(1)? },
+        Some("parity") => Family::Parity { vars: num(1)?, equations: num(2)?, k: num(3)? },
+        _ => return None,
+    };
+    Some((family, seed, xor))
+}
+
+/// `k` distinct variables out of `0..vars`.
+fn sample(rng: &mut Rng, vars: usize, k: usize) -> Vec<usize> {
+    let mut picked = Vec::with_capacity(k);
+    while picked.len() < k {
+        let var = rng.below(vars);
+        if !picked.contains(&var) {
+            picked.push(var);
+        }
+    }
+    picked
+}
+
+fn at_most_one(clauses: &mut Vec<Vec<Lit>>, lits: &[Lit]) {
+    for i in 0..lits.len() {
+        for j in i + 1..lits.len() {
+            clauses.push(vec![!lits[i], !lits[j]]);
+        }
+    }
+}
+
+fn ksat(rng: &mut Rng, vars: usize, ratio: f64, k: usize) -> Vec<Vec<Lit>> {
+    let count = (ratio * vars as f64).round() as usize;
+    (0..count)
+        .map(|_| {
+            sample(rng, vars, k).into_iter()
+                .map(|var| Lit::new(var, rng.chance(0.5)))
+                .collect()
+        })
+        .collect()
+}
+
+/// Variable `p * holes + h` puts pigeon `p` in hole `h`.
+fn pigeonhole(pigeons: usize, holes: usize) -> Vec<Vec<Lit>> {
+    let mut clauses = Vec::new();
+    for p in 0..pigeons {
+        clauses.push((0..holes).map(|h| Lit::new(p * holes + h, false)).collect());
+    }
+    for h in 0..holes {
+        let hole: Vec<Lit> = (0..pigeons).map(|p| Lit::new(p * holes + h, false)).collect();
+        at_most_one(&mut clauses, &hole);
+    }
+    clauses
+}
+
+/// Variable `v * colors + c` gives vertex `v` color `c`.
+fn coloring(rng: &mut Rng, vertices: usize, edges: usize, colors: usize) -> Vec<Vec<Lit>> {
+    let mut graph: Vec<(usize, usize)> = Vec::with_capacity(edges);
+    while graph.len() < edges {
+        let pair = sample(rng, vertices, 2);
+        let edge = (pair[0].min(pair[1]), pair[0].max(pair[1]));
+        if !graph.contains(&edge) {
+            graph.push(edge);
+        }
+    }
+
+    let mut clauses = Vec::new();
+    for v in 0..vertices {
+        let options: Vec<Lit> = (0..colors).map(|c| Lit::new(v * colors + c, false)).collect();
+        clauses.push(options.clone());
+        at_most_one(&mut clauses, &options);
+    }
+    for &(u, v) in graph.iter() {
+        for c in 0..colors {
+            clauses.push(vec![Lit::new(u * colors + c, true), Lit::new(v * colors + c, true)]);
+        }
+    }
+    clauses
+}
+
+/// Variable `row * n + col` places a queen. Every row needs one queen and
+/// no line on the board may hold two.
+fn queens(n: usize) -> Vec<Vec<Lit>> {
+    let square = |row: usize, col: usize| Lit::new(row * n + col, false);
+    let mut clauses = Vec::new();
+    for row in 0..n {
+        clauses.push((0..n).map(|col| square(row, col)).collect());
+        let line: Vec<Lit> = (0..n).map(|col| square(row, col)).collect();
+        at_most_one(&mut clauses, &line);
+    }
+    for col in 0..n {
+        let line: Vec<Lit> = (0..n).map(|row| square(row, col)).collect();
+        at_most_one(&mut clauses, &line);
+    }
+    for d in 0..2 * n - 1 {
+        let down: Vec<Lit> = (0..n)
+            .filter(|&row| d >= row && d - row < n)
+            .map(|row| square(row, d - row))
+            .collect();
+        let up: Vec<Lit> = (0..n)
+            .filter(|&row| d + row >= n - 1 && d + row - (n - 1) < n)
+            .map(|row| square(row, d + row - (n - 1)))
+            .collect();
+        at_most_one(&mut clauses, &down);
+        at_most_one(&mut clauses, &up);
+    }
+    clauses
+}
+
+/// Equations over `k` random variables whose right-hand sides agree with
+/// a hidden assignment, so the instance is always satisfiable. Each one is
+/// returned as literals whose exclusive or must be true.
+fn parity(rng: &mut Rng, vars: usize, equations: usize, k: usize) -> Vec<Vec<Lit>> {
+    let hidden: Vec<bool> = (0..vars).map(|_| rng.chance(0.5)).collect();
+    (0..equations)
+        .map(|_| {
+            let mut xor: Vec<Lit> = sample(rng, vars, k).into_iter()
+                .map(|var| Lit::new(var, rng.chance(0.5)))
+                .collect();
+            let odd = xor.iter().filter(|lit| hidden[lit.var()] != lit.is_negated()).count() % 2 == 1;
+            if !odd {
+                xor[0] = !xor[0];
+            }
+            xor
+        })
+        .collect()
+}
+
+/// Clauses forbidding every even assignment of `xor`, splitting it with
+/// fresh variables so that no piece has more than four literals.
+fn expand_xor(vars: &mut usize, clauses: &mut Vec<Vec<Lit>>, xor: &[Lit]) {
+    let mut xor = xor.to_vec();
+    while xor.len() > 4 {
+        // x1 ^ x2 ^ x3 ^ rest  becomes  t = x1 ^ x2 ^ x3 and t ^ rest.
+        let t = Lit::new(*vars, false);
+        *vars += 1;
+        let mut piece: Vec<Lit> = xor.drain(..3).collect();
+        piece.push(!t);
+        expand_xor(vars, clauses, &piece);
+        xor.insert(0, t);
+    }
+    for mask in 0..1usize << xor.len() {
+        // The clause is falsified by making the literals outside the mask
+        // true, so it rules out an even count when those are even.
+        if mask.count_ones() as usize % 2 == xor.len() % 2 {
+            let clause = xor.iter().enumerate()
+                .map(|(i, &lit)| if mask >> i & 1 == 1 { lit } else { !lit })
+                .collect();
+            clauses.push(clause);
+        }
+    }
+}
+
+fn main() {
+    let (family, seed, native_xor) = match parse_args() {
+        Some(parsed) => parsed,
+        None => { usage(); process::exit(1) },
+    };
+    let mut rng = Rng::new(seed);
+
+    let mut xors = Vec::new();
+    let (vars, clauses, name) = match family {
+        Family::KSat { vars, ratio, k } => {
+            if k == 0 || k > vars {
+                fail("K must be between 1 and VARS");
+            }
+            (vars, ksat(&mut rng, vars, ratio, k), format!("ksat vars={} ratio={} k={}", vars, ratio, k))
+        },
+        Family::Pigeonhole { pigeons, holes } => {
+            (pigeons * holes, pigeonhole(pigeons, holes), format!("php pigeons={} holes={}", pigeons, holes))
+        },
+        Family::Coloring { vertices, edges, colors } => {
+            if edges > vertices * vertices.saturating_sub(1) / 2 {
+                fail(&format!("A simple graph on {} vertices has at most {} edges",
+                              vertices, vertices * vertices.saturating_sub(1) / 2));
+            }
+            (vertices * colors, coloring(&mut rng, vertices, edges, colors),
+             format!("color vertices={} edges={} colors={}", vertices, edges, colors))
+        },
+        Family::Queens { n } => {
+            if n == 0 {
+                fail("N must be positive");
+            }
+            (n * n, queens(n), format!("queens n={}", n))
+        },
+        Family::Parity { vars, equations, k } => {
+            if k == 0 || k > vars {
+                fail("K must be between 1 and VARS");
+            }
+            let equations = parity(&mut rng, vars, equations, k);
+            let name = format!("parity vars={} equations={} k={}", vars, equations.len(), k);
+            if native_xor {
+                xors = equations;
+                (vars, Vec::new(), name)
+            } else {
+                let (mut total, mut clauses) = (vars, Vec::new());
+                for xor in equations.iter() {
+                    expand_xor(&mut total, &mut clauses, xor);
+                }
+                (total, clauses, name)
+            }
+        },
+    };
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let written = writeln!(out, "c {} seed={}", name, seed)
+        .and_then(|_| dimacs::write(&mut out, vars, &clauses))
+        .and_then(|_| {
+            for xor in xors.iter() {
+                let lits: Vec<String> = xor.iter().map(|lit| lit.to_dimacs().to_string()).collect();
+                writeln!(out, "x{} 0", lits.join(" "))?;
+            }
+            Ok(())
+        });
+    // A reader that stops early, like head, is no error.
+    match written {
+        Err(ref e) if e.kind() != io::ErrorKind::BrokenPipe => fail(&format!("Could not write the instance: {}", e)),
+        _ => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cue6_05::count::Counter;
+    use cue6_05::Solver;
+
+    fn models(vars: usize, clauses: &[Vec<Lit>]) -> String {
+        Counter::new().count(vars, clauses).unwrap().to_string()
+    }
+
+    #[test]
+    fn queens_have_their_known_solution_counts() {
+        for &(n, solutions) in [(1, "1"), (2, "0"), (3, "0"), (4, "2"), (5, "10"), (6, "4")].iter() {
+            assert_eq!(models(n * n, &queens(n)), solutions);
+        }
+    }
+
+    #[test]
+    fn pigeons_fit_only_with_enough_holes() {
+        for pigeons in 1..5 {
+            for holes in 1..5 {
+                // Each hole goes to at most one pigeon and every pigeon gets
+                // one, counted by inclusion-exclusion over the pigeons left out.
+                let ways: i64 = (0..pigeons + 1)
+                    .map(|out| {
+                        let choose: i64 = (0..out).map(|i| (pigeons - i) as i64).product::<i64>()
+                            / (1..out as i64 + 1).product::<i64>();
+                        let sign = if out % 2 == 0 { 1 } else { -1 };
+                        sign * choose * ((pigeons - out + 1) as i64).pow(holes as u32)
+                    })
+                    .sum();
+                assert_eq!(ways == 0, pigeons > holes);
+                assert_eq!(models(pigeons * holes, &pigeonhole(pigeons, holes)), ways.to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn random_families_have_the_requested_shape() {
+        let mut rng = Rng::new(1);
+        let clauses = ksat(&mut rng, 20, 4.26, 3);
+        assert_eq!(clauses.len(), 85);
+        for clause in clauses.iter() {
+            let mut vars: Vec<usize> = clause.iter().map(|lit| lit.var()).collect();
+            vars.sort();
+            vars.dedup();
+            assert_eq!(vars.len(), 3);
+        }
+        // A clause and three exclusions per vertex, three per edge.
+        assert_eq!(coloring(&mut rng, 10, 12, 3).len(), 10 * 4 + 12 * 3);
+    }
+
+    #[test]
+    fn parity_instances_are_satisfiable_in_either_form() {
+        let mut rng = Rng::new(2);
+        for _ in 0..50 {
+            let vars = 3 + rng.below(20);
+            let k = 1 + rng.below(vars.min(7));
+            let count = 1 + rng.below(2 * vars);
+            let equations = parity(&mut rng, vars, count, k);
+            let mut solver = Solver::new();
+            let (mut total, mut clauses) = (vars, Vec::new());
+            for xor in equations.iter() {
+                solver.add_xor(xor);
+                expand_xor(&mut total, &mut clauses, xor);
+            }
+            assert!(solver.solve());
+            let mut solver = Solver::new();
+            for clause in clauses.iter() {
+                solver.add_clause(clause);
+            }
+            assert!(solver.solve());
+        }
+    }
+
+    #[test]
+    fn expanded_xors_keep_their_parity() {
+        let mut rng = Rng::new(3);
+        for len in 1..9 {
+            let xor: Vec<Lit> = (0..len).map(|var| Lit::new(var, rng.chance(0.5))).collect();
+            let (mut vars, mut clauses) = (len, Vec::new());
+            expand_xor(&mut vars, &mut clauses, &xor);
+            assert!(clauses.iter().all(|clause| clause.len() <= 4));
+            let mut solver = Solver::new();
+            for clause in clauses.iter() {
+                solver.add_clause(clause);
+            }
+            for bits in 0..1usize << len {
+                let fixed: Vec<Lit> = (0..len).map(|var| Lit::new(var, bits >> var & 1 == 0)).collect();
+                let odd = xor.iter().filter(|lit| (bits >> lit.var() & 1 == 1) != lit.is_negated()).count() % 2 == 1;
+                assert_eq!(solver.solve_with_assumptions(&fixed), odd);
+            }
+        }
+    }
+}
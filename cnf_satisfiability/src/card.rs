@@ -0,0 +1,312 @@
+use std::cmp;
+use std::collections::BTreeMap;
+
+use dimacs::Cnf;
+use solver::{Lit, Solver};
+
+/// Anything the encodings can add variables and clauses to: a solver, or a
+/// formula that is still being built.
+pub trait Sink {
+    fn new_var(&mut self) -> usize;
+    fn add_clause(&mut self, lits: &[Lit]) -> bool;
+}
+
+impl Sink for Solver {
+    fn new_var(&mut self) -> usize {
+        Solver::new_var(self)
+    }
+
+    fn add_clause(&mut self, lits: &[Lit]) -> bool {
+        Solver::add_clause(self, lits)
+    }
+}
+
+impl Sink for Cnf {
+    fn new_var(&mut self) -> usize {
+        self.vars += 1;
+        self.vars - 1
+    }
+
+    fn add_clause(&mut self, lits: &[Lit]) -> bool {
+        self.clauses.push(lits.to_vec());
+        true
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Relation {
+    AtMost,
+    AtLeast,
+    Exactly,
+}
+
+/// Translation used for cardinality and pseudo-Boolean constraints.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Encoding {
+    SequentialCounter,
+    Totalizer,
+}
+
+/// A linear pseudo-Boolean constraint `sum(weight * lit) REL bound`, kept
+/// with positive weights only. Cardinality constraints have unit weights.
+#[derive(Clone, Debug)]
+pub struct Linear {
+    pub terms: Vec<(u64, Lit)>,
+    pub relation: Relation,
+    pub bound: i64,
+}
+
+impl Linear {
+    /// Builds a constraint from arbitrary integer weights. A negative term
+    /// `-w * l` is rewritten as `w * !l - w`, moving the constant into the
+    /// bound.
+    pub fn new(terms: &[(i64, Lit)], relation: Relation, bound: i64) -> Linear {
+        let mut bound = bound;
+        let mut normalized = Vec::with_capacity(terms.len());
+        for &(weight, lit) in terms.iter() {
+            if weight < 0 {
+                normalized.push((weight.unsigned_abs(), !lit));
+                bound -= weight;
+            } else if weight > 0 {
+                normalized.push((weight as u64, lit));
+            }
+        }
+        Linear {
+            terms: normalized,
+            relation,
+            bound,
+        }
+    }
+
+    pub fn cardinality(lits: &[Lit], relation: Relation, bound: i64) -> Linear {
+        let terms: Vec<(i64, Lit)> = lits.iter().map(|&lit| (1, lit)).collect();
+        Linear::new(&terms, relation, bound)
+    }
+
+    /// Adds clauses equisatisfiable with the constraint to `sink`.
+    pub fn encode<S: Sink>(&self, sink: &mut S, encoding: Encoding) {
+        let total: i64 = self.terms.iter().map(|&(weight, _)| weight as i64).sum();
+        let negated: Vec<(u64, Lit)> = self.terms.iter()
+            .map(|&(weight, lit)| (weight, !lit))
+            .collect();
+        if self.relation != Relation::AtLeast {
+            at_most(sink, &self.terms, self.bound, encoding);
+        }
+        if self.relation != Relation::AtMost {
+            // At least k of the weight is true iff at most total - k is false.
+            at_most(sink, &negated, total - self.bound, encoding);
+        }
+    }
+}
+
+/// Encodes `sum(weight * lit) <= bound`.
+fn at_most<S: Sink>(sink: &mut S, terms: &[(u64, Lit)], bound: i64, encoding: Encoding) {
+    if bound < 0 {
+        sink.add_clause(&[]);
+        return;
+    }
+    let bound = bound as u64;
+    let mut kept = Vec::with_capacity(terms.len());
+    for &(weight, lit) in terms.iter() {
+        if weight > bound {
+            sink.add_clause(&[!lit]);
+        } else {
+            kept.push((weight, lit));
+        }
+    }
+    if kept.iter().map(|&(weight, _)| weight).sum::<u64>() <= bound {
+        return;
+    }
+
+    match encoding {
+        Encoding::SequentialCounter => sequential_counter(sink, &kept, bound),
+        Encoding::Totalizer => {
+            Totalizer::new(sink, &kept, bound + 1).enforce_below(sink, bound + 1);
+        },
+    }
+}
+
+/// Sequential weight counter. Register `s[i][j]` is implied when the first
+/// `i + 1` terms add up to at least `j + 1`; with unit weights this is the
+/// usual sequential counter for cardinality. Every weight must be at most
+/// `bound`, which must be positive.
+fn sequential_counter<S: Sink>(sink: &mut S, terms: &[(u64, Lit)], bound: u64) {
+    let k = bound as usize;
+    let mut prev: Vec<Lit> = Vec::new();
+    for (i, &(weight, lit)) in terms.iter().enumerate() {
+        let weight = weight as usize;
+        let last = i + 1 == terms.len();
+
+        // Too much weight once this term is added.
+        if !prev.is_empty() && weight <= k {
+            sink.add_clause(&[!prev[k - weight], !lit]);
+        }
+        if last {
+            break;
+        }
+
+        let regs: Vec<Lit> = (0..k).map(|_| Lit::new(sink.new_var(), false)).collect();
+        for j in 0..k {
+            if j < weight {
+                sink.add_clause(&[!lit, regs[j]]);
+            }
+            if !prev.is_empty() {
+                sink.add_clause(&[!prev[j], regs[j]]);
+                if j + weight < k {
+                    sink.add_clause(&[!prev[j], !lit, regs[j + weight]]);
+                }
+            }
+        }
+        prev = regs;
+    }
+}
+
+/// Generalized totalizer over weighted input literals.
+///
+/// Each output literal stands for a sum of input weights and is implied
+/// whenever the true inputs add up to at least that sum. Sums are capped so
+/// that the last output means "the cap or more"; this keeps the encoding small
+/// when it is only used to enforce bounds below the cap.
+pub struct Totalizer {
+    outputs: Vec<(u64, Lit)>,
+}
+
+impl Totalizer {
+    pub fn new<S: Sink>(sink: &mut S, inputs: &[(u64, Lit)], cap: u64) -> Totalizer {
+        let outputs = if inputs.is_empty() {
+            Vec::new()
+        } else {
+            build(sink, inputs, cap)
+        };
+        Totalizer { outputs }
+    }
+
+    /// Literals whose negation rules out every sum of at least `bound`.
+    pub fn at_least(&self, bound: u64) -> Vec<Lit> {
+        self.outputs.iter()
+            .filter(|&&(sum, _)| sum >= bound)
+            .map(|&(_, lit)| lit)
+            .collect()
+    }
+
+    /// Forbids the inputs from adding up to `bound` or more.
+    pub fn enforce_below<S: Sink>(&self, sink: &mut S, bound: u64) -> bool {
+        for lit in self.at_least(bound) {
+            if !sink.add_clause(&[!lit]) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn build<S: Sink>(sink: &mut S, inputs: &[(u64, Lit)], cap: u64) -> Vec<(u64, Lit)> {
+    if inputs.len() == 1 {
+        let (weight, lit) = inputs[0];
+        return vec![(cmp::min(weight, cap), lit)];
+    }
+
+    let mid = inputs.len() / 2;
+    let left = build(sink, &inputs[..mid], cap);
+    let right = build(sink, &inputs[mid..], cap);
+
+    let mut sums: BTreeMap<u64, Lit> = BTreeMap::new();
+    for &(sum, _) in left.iter().chain(right.iter()) {
+        sums.entry(sum).or_insert_with(|| Lit::new(sink.new_var(), false));
+    }
+    for &(a, _) in left.iter() {
+        for &(b, _) in right.iter() {
+            let sum = cmp::min(a + b, cap);
+            sums.entry(sum).or_insert_with(|| Lit::new(sink.new_var(), false));
+        }
+    }
+
+    for &(sum, lit) in left.iter().chain(right.iter()) {
+        sink.add_clause(&[!lit, sums[&sum]]);
+    }
+    for &(a, la) in left.iter() {
+        for &(b, lb) in right.iter() {
+            let sum = cmp::min(a + b, cap);
+            sink.add_clause(&[!la, !lb, sums[&sum]]);
+        }
+    }
+    sums.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rng::Rng;
+    use testing::{assignments, holds, solver_with};
+
+    fn holds_linear(linear: &Linear, values: &[bool]) -> bool {
+        let sum: i64 = linear.terms.iter()
+            .filter(|&&(_, lit)| holds(lit, values))
+            .map(|&(weight, _)| weight as i64)
+            .sum();
+        match linear.relation {
+            Relation::AtMost => sum <= linear.bound,
+            Relation::AtLeast => sum >= linear.bound,
+            Relation::Exactly => sum == linear.bound,
+        }
+    }
+
+    /// The assumptions fixing the first `values.len()` variables.
+    fn fixing(values: &[bool]) -> Vec<Lit> {
+        values.iter().enumerate().map(|(var, &value)| Lit::new(var, !value)).collect()
+    }
+
+    fn random_linear(rng: &mut Rng, vars: usize, unit: bool) -> Linear {
+        let terms: Vec<(i64, Lit)> = (0..1 + rng.below(6))
+            .map(|_| {
+                let weight = if unit { 1 } else { rng.below(9) as i64 - 4 };
+                (weight, Lit::new(rng.below(vars), rng.chance(0.5)))
+            })
+            .collect();
+        let relation = [Relation::AtMost, Relation::AtLeast, Relation::Exactly][rng.below(3)];
+        Linear::new(&terms, relation, rng.below(14) as i64 - 3)
+    }
+
+    #[test]
+    fn encodings_allow_exactly_the_solutions() {
+        let mut rng = Rng::new(1);
+        for _ in 0..300 {
+            let vars = 1 + rng.below(6);
+            let unit = rng.chance(0.5);
+            let linear = random_linear(&mut rng, vars, unit);
+            for &encoding in [Encoding::SequentialCounter, Encoding::Totalizer].iter() {
+                // Through a formula, as the front-ends do, then into a solver.
+                let mut cnf = Cnf { vars, clauses: Vec::new(), constraints: Vec::new(), xors: Vec::new() };
+                linear.encode(&mut cnf, encoding);
+                let mut solver = solver_with(cnf.vars, &cnf.clauses);
+                for values in assignments(vars) {
+                    assert_eq!(solver.solve_with_assumptions(&fixing(&values)), holds_linear(&linear, &values),
+                               "{:?} {:?} {:?}", encoding, linear, values);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn totalizer_bounds_the_weight() {
+        let mut rng = Rng::new(2);
+        for _ in 0..200 {
+            let vars = 1 + rng.below(6);
+            let inputs: Vec<(u64, Lit)> = (0..vars).map(|var| (1 + rng.below(5) as u64, Lit::new(var, false))).collect();
+            let total: u64 = inputs.iter().map(|&(weight, _)| weight).sum();
+            let cap = 1 + rng.below(total as usize) as u64;
+            let mut solver = solver_with(vars, &[]);
+            let totalizer = Totalizer::new(&mut solver, &inputs, cap);
+            // Tightening the bound step by step, as MaxSAT does.
+            for bound in (1..cap + 1).rev() {
+                if !totalizer.enforce_below(&mut solver, bound) {
+                    break;
+                }
+                for values in assignments(vars) {
+                    let sum: u64 = inputs.iter().filter(|&&(_, lit)| holds(lit, &values)).map(|&(weight, _)| weight).sum();
+                    assert_eq!(solver.solve_with_assumptions(&fixing(&values)), sum < bound);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use solver::{Lit, Solver};
+
+/// Finds a subset of `clauses` that is unsatisfiable together with the
+/// `background` clauses, as indices into `clauses`, or None if all of them
+/// are satisfiable together. Background clauses are never part of the core.
+///
+/// Each clause is guarded by a selector variable that is assumed true, so
+/// conflict analysis reports the clauses it relied on as failed assumptions.
+/// With `minimize`, clauses are then dropped one at a time while the rest
+/// stays unsatisfiable, which leaves a minimal core.
+pub fn unsat_core(vars: usize, clauses: &[Vec<Lit>], background: &[Vec<Lit>], minimize: bool)
+    -> Option<Vec<usize>>
+{
+    let vars = clauses.iter()
+        .chain(background.iter())
+        .flat_map(|clause| clause.iter().map(|lit| lit.var() + 1))
+        .fold(vars, usize::max);
+    let mut solver = Solver::new();
+    while solver.num_vars() < vars {
+        solver.new_var();
+    }
+    for clause in background.iter() {
+        solver.add_clause(clause);
+    }
+    let mut selectors = Vec::with_capacity(clauses.len());
+    for clause in clauses.iter() {
+        let selector = Lit::new(solver.new_var(), false);
+        let mut guarded = clause.clone();
+        guarded.push(!selector);
+        solver.add_clause(&guarded);
+        selectors.push(selector);
+    }
+
+    if solver.solve_with_assumptions(&selectors) {
+        return None;
+    }
+    let mut core = failed_clauses(&solver, &selectors);
+
+    if minimize {
+        let mut needed = Vec::new();
+        let mut unknown = core;
+        while let Some(cr) = unknown.pop() {
+            let assumptions: Vec<Lit> = needed.iter()
+                .chain(unknown.iter())
+                .map(|&c| selectors[c])
+                .collect();
+            if solver.solve_with_assumptions(&assumptions) {
+                needed.push(cr);
+            } else {
+                // The refutation without cr may need even fewer clauses.
+                let failed = failed_clauses(&solver, &selectors);
+                unknown.retain(|c| failed.binary_search(c).is_ok());
+            }
+        }
+        needed.sort();
+        core = needed;
+    }
+    Some(core)
+}
+
+/// Indices of the clauses whose selectors the last solve failed on, sorted.
+fn failed_clauses(solver: &Solver, selectors: &[Lit]) -> Vec<usize> {
+    let first = match selectors.first() {
+        Some(selector) => selector.var(),
+        None => return Vec::new(),
+    };
+    let mut failed: Vec<usize> = solver.failed_assumptions()
+        .iter()
+        .map(|lit| lit.var() - first)
+        .collect();
+    failed.sort();
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rng::Rng;
+    use testing::{models, random_clauses};
+
+    fn unsat(vars: usize, core: &[usize], clauses: &[Vec<Lit>], background: &[Vec<Lit>]) -> bool {
+        let mut chosen: Vec<Vec<Lit>> = core.iter().map(|&i| clauses[i].clone()).collect();
+        chosen.extend(background.iter().cloned());
+        models(vars, &chosen).is_empty()
+    }
+
+    #[test]
+    fn cores_are_unsatisfiable_and_minimal() {
+        let mut rng = Rng::new(1);
+        for _ in 0..400 {
+            let vars = 1 + rng.below(8);
+            let count = 1 + rng.below(8 * vars);
+            let clauses = random_clauses(&mut rng, vars, count, 3);
+            let count = rng.below(2 * vars);
+            let background = random_clauses(&mut rng, vars, count, 3);
+            let minimize = rng.chance(0.5);
+            match unsat_core(vars, &clauses, &background, minimize) {
+                None => assert!(!unsat(vars, &(0..clauses.len()).collect::<Vec<_>>(), &clauses, &background)),
+                Some(core) => {
+                    assert!(core.windows(2).all(|pair| pair[0] < pair[1]));
+                    assert!(core.iter().all(|&i| i < clauses.len()));
+                    assert!(unsat(vars, &core, &clauses, &background));
+                    if minimize {
+                        for i in 0..core.len() {
+                            let mut smaller = core.clone();
+                            smaller.remove(i);
+                            assert!(!unsat(vars, &smaller, &clauses, &background));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn background_alone_gives_an_empty_core() {
+        let lit = |num| Lit::from_dimacs(num);
+        let background = vec![vec![lit(1)], vec![lit(-1)]];
+        assert_eq!(unsat_core(1, &[vec![lit(1), lit(2)]], &background, true), Some(Vec::new()));
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use bignum::BigNum;
+use solver::Lit;
+
+/// Exact model counter. Splits the residual formula into variable-disjoint
+/// components, counts each one separately and caches the count of every
+/// component it has seen.
+#[derive(Default)]
+pub struct Counter {
+    cache: HashMap<Vec<Vec<Lit>>, BigNum>,
+    pub decisions: usize,
+    /// Branches refuted by unit propagation.
+    pub conflicts: usize,
+    pub cache_hits: usize,
+    conflict_budget: Option<usize>,
+    decision_budget: Option<usize>,
+    deadline: Option<Instant>,
+}
+
+impl Counter {
+    pub fn new() -> Counter {
+        Counter::default()
+    }
+
+    /// Lets `count` run into `conflicts` more conflicts from now on before
+    /// it gives up.
+    pub fn conflict_limit(&mut self, conflicts: usize) -> &mut Self {
+        self.conflict_budget = Some(self.conflicts + conflicts);
+        self
+    }
+
+    /// Lets `count` make `decisions` more decisions from now on.
+    pub fn decision_limit(&mut self, decisions: usize) -> &mut Self {
+        self.decision_budget = Some(self.decisions + decisions);
+        self
+    }
+
+    /// Makes `count` give up once `time` has passed from now.
+    pub fn time_limit(&mut self, time: Duration) -> &mut Self {
+        self.deadline = Some(Instant::now() + time);
+        self
+    }
+
+    /// Number of assignments to variables `0..vars` satisfying `clauses`,
+    /// or None if the counter ran out of one of its limits first.
+    pub fn count(&mut self, vars: usize, clauses: &[Vec<Lit>]) -> Option<BigNum> {
+        let mentioned = count_vars(clauses);
+        Some(self.count_formula(clauses.to_vec())?.shl(vars - mentioned))
+    }
+
+    /// Counts the assignments over the variables mentioned in `clauses`.
+    fn count_formula(&mut self, mut clauses: Vec<Vec<Lit>>) -> Option<BigNum> {
+        let before = count_vars(&clauses);
+        let assigned = match propagate(&mut clauses) {
+            Some(assigned) => assigned,
+            None => {
+                self.conflicts += 1;
+                return Some(BigNum::from_u64(0));
+            },
+        };
+        let after = count_vars(&clauses);
+
+        let mut total = BigNum::from_u64(1).shl(before - assigned - after);
+        for component in components(clauses) {
+            let count = self.count_component(component)?;
+            if count.is_zero() {
+                return Some(count);
+            }
+            total = total.mul(&count);
+        }
+        Some(total)
+    }
+
+    fn count_component(&mut self, mut component: Vec<Vec<Lit>>) -> Option<BigNum> {
+        for clause in component.iter_mut() {
+            clause.sort();
+        }
+        component.sort();
+        if let Some(count) = self.cache.get(&component) {
+            self.cache_hits += 1;
+            return Some(count.clone());
+        }
+
+        if self.interrupted() {
+            return None;
+        }
+        self.decisions += 1;
+        let var = branch_var(&component);
+        let mut total = BigNum::from_u64(0);
+        for &negated in [false, true].iter() {
+            let mut branch = component.clone();
+            branch.push(vec![Lit::new(var, negated)]);
+            total = total.add(&self.count_formula(branch)?);
+        }
+        self.cache.insert(component, total.clone());
+        Some(total)
+    }
+
+    /// Whether one of the limits ran out.
+    fn interrupted(&self) -> bool {
+        self.conflict_budget.is_some_and(|n| self.conflicts >= n)
+            || self.decision_budget.is_some_and(|n| self.decisions >= n)
+            || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+fn count_vars(clauses: &[Vec<Lit>]) -> usize {
+    let mut vars: Vec<usize> = clauses.iter()
+        .flat_map(|clause| clause.iter().map(|lit| lit.var()))
+        .collect();
+    vars.sort();
+    vars.dedup();
+    vars.len()
+}
+
+/// Applies unit propagation in place, dropping satisfied clauses and false
+/// literals. Returns how many variables were fixed, or None on a conflict.
+fn propagate(clauses: &mut Vec<Vec<Lit>>) -> Option<usize> {
+    let mut assigned = 0;
+    loop {
+        let unit = match clauses.iter().find(|clause| clause.len() <= 1) {
+            Some(clause) if clause.is_empty() => return None,
+            Some(clause) => clause[0],
+            None => return Some(assigned),
+        };
+        assigned += 1;
+        clauses.retain(|clause| !clause.contains(&unit));
+        for clause in clauses.iter_mut() {
+            clause.retain(|&lit| lit != !unit);
+        }
+    }
+}
+
+/// Groups clauses into sets that share no variables.
+fn components(clauses: Vec<Vec<Lit>>) -> Vec<Vec<Vec<Lit>>> {
+    let vars = clauses.iter()
+        .flat_map(|clause| clause.iter().map(|lit| lit.var() + 1))
+        .max()
+        .unwrap_or(0);
+    let mut parent: Vec<usize> = (0..vars).collect();
+    fn find(parent: &mut [usize], mut var: usize) -> usize {
+        while parent[var] != var {
+            parent[var] = parent[parent[var]];
+            var = parent[var];
+        }
+        var
+    }
+    for clause in clauses.iter() {
+        let root = find(&mut parent, clause[0].var());
+        for lit in clause[1..].iter() {
+            let other = find(&mut parent, lit.var());
+            parent[other] = root;
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<Vec<Lit>>> = BTreeMap::new();
+    for clause in clauses {
+        let root = find(&mut parent, clause[0].var());
+        groups.entry(root).or_default().push(clause);
+    }
+    groups.into_values().collect()
+}
+
+/// The variable occurring in the most clauses.
+fn branch_var(clauses: &[Vec<Lit>]) -> usize {
+    let mut occurs: HashMap<usize, usize> = HashMap::new();
+    for clause in clauses.iter() {
+        for lit in clause.iter() {
+            *occurs.entry(lit.var()).or_insert(0) += 1;
+        }
+    }
+    occurs.into_iter()
+        .max_by_key(|&(var, n)| (n, std::cmp::Reverse(var)))
+        .unwrap()
+        .0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rng::Rng;
+    use testing::{models, random_clauses};
+
+    #[test]
+    fn count_matches_brute_force() {
+        let mut rng = Rng::new(1);
+        for _ in 0..300 {
+            let vars = 1 + rng.below(10);
+            let count = rng.below(3 * vars + 1);
+            let clauses = random_clauses(&mut rng, vars, count, 3);
+            let mut counter = Counter::new();
+            let expected = models(vars, &clauses).len() as u64;
+            assert_eq!(counter.count(vars, &clauses), Some(BigNum::from_u64(expected)));
+        }
+    }
+
+    #[test]
+    fn components_multiply() {
+        // Twenty independent copies of (x or y) have 3^20 models, found
+        // without branching on every combination.
+        let clauses: Vec<Vec<Lit>> = (0..20).map(|i| vec![Lit::new(2 * i, false), Lit::new(2 * i + 1, false)]).collect();
+        let mut counter = Counter::new();
+        assert_eq!(counter.count(42, &clauses).unwrap().to_string(), (3u64.pow(20) * 4).to_string());
+        assert!(counter.decisions <= 40);
+    }
+
+    #[test]
+    fn stops_at_a_limit() {
+        // A chain of (x or y) needs a branch for every few variables.
+        let clauses: Vec<Vec<Lit>> = (0..29).map(|i| vec![Lit::new(i, false), Lit::new(i + 1, false)]).collect();
+        let mut counter = Counter::new();
+        counter.decision_limit(5);
+        assert_eq!(counter.count(30, &clauses), None);
+        assert_eq!(counter.decisions, 5);
+
+        // Nothing counted before the stop was cached, so the counter
+        // still gets it right once it has room to finish: F(32) models.
+        counter.decision_limit(1000);
+        assert_eq!(counter.count(30, &clauses), Some(BigNum::from_u64(2_178_309)));
+    }
+}
@@ -0,0 +1,271 @@
+use std::io;
+use std::io::{BufRead, Write};
+
+use card::{Linear, Relation};
+use solver::Lit;
+
+/// A formula in conjunctive normal form as read from a DIMACS file, along
+/// with any cardinality, pseudo-Boolean and XOR constraints it contained.
+pub struct Cnf {
+    pub vars: usize,
+    pub clauses: Vec<Vec<Lit>>,
+    pub constraints: Vec<Linear>,
+    /// Each XOR constraint holds when an odd number of its literals is true.
+    pub xors: Vec<Vec<Lit>>,
+}
+
+/// Reads a `p cnf VARS CLAUSES` problem. Clauses may span several lines and
+/// are terminated by 0.
+///
+/// Two extra line types carry linear constraints, where REL is one of `<=`,
+/// `>=` or `=`:
+///
+///   k REL BOUND LIT LIT ... 0            cardinality constraint
+///   w REL BOUND WEIGHT LIT WEIGHT LIT ... 0   pseudo-Boolean constraint
+///
+/// XOR constraints are written `x1 -2 3 0`, or with a space after the `x`,
+/// and state that the exclusive or of the literals is true.
+pub fn parse<R: BufRead>(input: R) -> Result<Cnf, String> {
+    let mut cnf = None;
+    let mut clause = Vec::new();
+
+    for line in input.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.first() {
+            None | Some(&"c") => continue,
+            Some(&"%") => break,
+            Some(&"p") => {
+                if words.get(1) != Some(&"cnf") {
+                    return Err(String::from("error with p directive"));
+                }
+                let vars = match words.get(2).and_then(|v| v.parse::<usize>().ok()) {
+                    Some(vars) => vars,
+                    None => return Err(String::from("error with p directive")),
+                };
+                let clauses = match words.get(3).and_then(|c| c.parse::<usize>().ok()) {
+                    Some(clauses) => clauses,
+                    None => return Err(String::from("error with p directive")),
+                };
+                cnf = Some(Cnf {
+                    vars,
+                    clauses: Vec::with_capacity(clauses),
+                    constraints: Vec::new(),
+                    xors: Vec::new(),
+                });
+            },
+            Some(&"k") | Some(&"w") => {
+                let cnf = match cnf {
+                    Some(ref mut cnf) => cnf,
+                    None => return Err(String::from("constraint before p directive")),
+                };
+                let linear = parse_linear(&words)?;
+                for &(_, lit) in linear.terms.iter() {
+                    if lit.var() >= cnf.vars {
+                        cnf.vars = lit.var() + 1;
+                    }
+                }
+                cnf.constraints.push(linear);
+            },
+            Some(word) if word.starts_with('x') => {
+                let cnf = match cnf {
+                    Some(ref mut cnf) => cnf,
+                    None => return Err(String::from("xor before p directive")),
+                };
+                let mut xor = Vec::new();
+                let first = &word[1..];
+                let rest = words[1..].iter().cloned();
+                for word in Some(first).into_iter().filter(|w| !w.is_empty()).chain(rest) {
+                    let num = match word.parse::<isize>() {
+                        Ok(num) => num,
+                        Err(_) => return Err(format!("Unknown {}", word)),
+                    };
+                    if num == 0 {
+                        break;
+                    }
+                    let lit = Lit::from_dimacs(num);
+                    if lit.var() >= cnf.vars {
+                        cnf.vars = lit.var() + 1;
+                    }
+                    xor.push(lit);
+                }
+                cnf.xors.push(xor);
+            },
+            Some(_) => {
+                let cnf = match cnf {
+                    Some(ref mut cnf) => cnf,
+                    None => return Err(String::from("clause before p directive")),
+                };
+                for word in words {
+                    let num = match word.parse::<isize>() {
+                        Ok(num) => num,
+                        Err(_) => return Err(format!("Unknown {}", word)),
+                    };
+                    if num == 0 {
+                        cnf.clauses.push(clause);
+                        clause = Vec::new();
+                    } else {
+                        let lit = Lit::from_dimacs(num);
+                        if lit.var() >= cnf.vars {
+                            cnf.vars = lit.var() + 1;
+                        }
+                        clause.push(lit);
+                    }
+                }
+            },
+        }
+    }
+
+    match cnf {
+        Some(mut cnf) => {
+            if !clause.is_empty() {
+                cnf.clauses.push(clause);
+            }
+            Ok(cnf)
+        },
+        None => Err(String::from("missing p directive")),
+    }
+}
+
+/// Parses a `k` or `w` constraint line.
+fn parse_linear(words: &[&str]) -> Result<Linear, String> {
+    let weighted = words[0] == "w";
+    let relation = match words.get(1) {
+        Some(&"<=") => Relation::AtMost,
+        Some(&">=") => Relation::AtLeast,
+        Some(&"=") => Relation::Exactly,
+        _ => return Err(format!("error with {} directive", words[0])),
+    };
+    let mut nums = Vec::with_capacity(words.len());
+    for word in words[2..].iter() {
+        match word.parse::<i64>() {
+            Ok(num) => nums.push(num),
+            Err(_) => return Err(format!("Unknown {}", word)),
+        }
+    }
+    if nums.len() < 2 || nums[nums.len() - 1] != 0 {
+        return Err(format!("error with {} directive", words[0]));
+    }
+    let bound = nums[0];
+    let body = &nums[1..nums.len() - 1];
+
+    let mut terms = Vec::with_capacity(body.len());
+    if weighted {
+        if body.len() % 2 != 0 {
+            return Err(String::from("w directive needs WEIGHT LIT pairs"));
+        }
+        for pair in body.chunks(2) {
+            if pair[1] == 0 {
+                return Err(String::from("error with w directive"));
+            }
+            terms.push((pair[0], Lit::from_dimacs(pair[1] as isize)));
+        }
+    } else {
+        for &num in body.iter() {
+            if num == 0 {
+                return Err(String::from("error with k directive"));
+            }
+            terms.push((1, Lit::from_dimacs(num as isize)));
+        }
+    }
+    Ok(Linear::new(&terms, relation, bound))
+}
+
+/// Writes clauses as a `p cnf` problem.
+pub fn write<W: Write>(out: &mut W, vars: usize, clauses: &[Vec<Lit>]) -> io::Result<()> {
+    writeln!(out, "p cnf {} {}", vars, clauses.len())?;
+    for clause in clauses.iter() {
+        for lit in clause.iter() {
+            write!(out, "{} ", lit.to_dimacs())?;
+        }
+        writeln!(out, "0")?;
+    }
+    Ok(())
+}
+
+/// A weighted partial MaxSAT instance. Soft clauses carry the weight paid
+/// when they are falsified.
+pub struct Wcnf {
+    pub vars: usize,
+    pub hard: Vec<Vec<Lit>>,
+    pub soft: Vec<(u64, Vec<Lit>)>,
+}
+
+/// Reads a WCNF problem, either with a `p wcnf VARS CLAUSES TOP` header
+/// where every clause starts with its weight and weights of at least TOP
+/// are hard, or in the headerless format where hard clauses start with `h`.
+pub fn parse_wcnf<R: BufRead>(input: R) -> Result<Wcnf, String> {
+    let mut wcnf = Wcnf {
+        vars: 0,
+        hard: Vec::new(),
+        soft: Vec::new(),
+    };
+    let mut top = None;
+    // Weight of the clause being read; None marks a hard clause.
+    let mut weight: Option<Option<u64>> = None;
+    let mut clause = Vec::new();
+
+    for line in input.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.first() {
+            None | Some(&"c") => continue,
+            Some(&"%") => break,
+            Some(&"p") => {
+                if words.get(1) != Some(&"wcnf") {
+                    return Err(String::from("error with p directive"));
+                }
+                match words.get(2).and_then(|v| v.parse::<usize>().ok()) {
+                    Some(vars) => wcnf.vars = vars,
+                    None => return Err(String::from("error with p directive")),
+                }
+                top = match words.get(4) {
+                    Some(top) => match top.parse::<u64>() {
+                        Ok(top) => Some(top),
+                        Err(_) => return Err(String::from("error with p directive")),
+                    },
+                    None => None,
+                };
+            },
+            Some(_) => {
+                for word in words {
+                    if weight.is_none() {
+                        weight = if word == "h" {
+                            Some(None)
+                        } else {
+                            match word.parse::<u64>() {
+                                Ok(w) if top.is_some_and(|top| w >= top) => Some(None),
+                                Ok(w) => Some(Some(w)),
+                                Err(_) => return Err(format!("Unknown {}", word)),
+                            }
+                        };
+                        continue;
+                    }
+
+                    let num = match word.parse::<isize>() {
+                        Ok(num) => num,
+                        Err(_) => return Err(format!("Unknown {}", word)),
+                    };
+                    if num == 0 {
+                        match weight.take().unwrap() {
+                            Some(w) => wcnf.soft.push((w, clause)),
+                            None => wcnf.hard.push(clause),
+                        }
+                        clause = Vec::new();
+                    } else {
+                        let lit = Lit::from_dimacs(num);
+                        if lit.var() >= wcnf.vars {
+                            wcnf.vars = lit.var() + 1;
+                        }
+                        clause.push(lit);
+                    }
+                }
+            },
+        }
+    }
+
+    if weight.is_some() {
+        return Err(String::from("clause missing its terminating 0"));
+    }
+    Ok(wcnf)
+}
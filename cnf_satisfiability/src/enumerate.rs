@@ -0,0 +1,103 @@
+use solver::{Lit, Solver};
+
+/// Lists every satisfying assignment of the solver's clauses, restricted to
+/// `projection`, by adding a blocking clause after each model. An empty
+/// projection means every variable. Each model is handed to `on_model` as
+/// one literal per projected variable. Returns the number of models and
+/// whether all of them were found, which is false if the solver ran out of
+/// one of its limits.
+///
+/// The blocking clauses stay in the solver afterwards.
+pub fn enumerate<F>(solver: &mut Solver, projection: &[usize], mut on_model: F) -> (usize, bool)
+    where F: FnMut(&[Lit])
+{
+    let vars: Vec<usize> = if projection.is_empty() {
+        (0..solver.num_vars()).collect()
+    } else {
+        projection.to_vec()
+    };
+
+    let mut models = 0;
+    loop {
+        match solver.solve_limited(&[]) {
+            Some(true) => {},
+            Some(false) => return (models, true),
+            None => return (models, false),
+        }
+        let model: Vec<Lit> = vars.iter()
+            .map(|&var| Lit::new(var, solver.model_value(Lit::new(var, false)) != Some(true)))
+            .collect();
+        models += 1;
+        on_model(&model);
+
+        let block: Vec<Lit> = model.iter().map(|&lit| !lit).collect();
+        if !solver.add_clause(&block) {
+            return (models, true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rng::Rng;
+    use testing::{models, random_clauses, solver_with};
+
+    /// The value each literal of a model gives its variable.
+    fn values(model: &[Lit]) -> Vec<bool> {
+        model.iter().map(|lit| !lit.is_negated()).collect()
+    }
+
+    #[test]
+    fn enumeration_matches_brute_force() {
+        let mut rng = Rng::new(2);
+        for _ in 0..300 {
+            let vars = 1 + rng.below(8);
+            let count = rng.below(3 * vars + 1);
+            let clauses = random_clauses(&mut rng, vars, count, 3);
+            let mut solver = solver_with(vars, &clauses);
+            let mut found = Vec::new();
+            let (count, complete) = enumerate(&mut solver, &[], |model| found.push(values(model)));
+            assert!(complete);
+            assert_eq!(count, found.len());
+            found.sort();
+            assert_eq!(found, models(vars, &clauses));
+        }
+    }
+
+    #[test]
+    fn projection_lists_each_restriction_once() {
+        let mut rng = Rng::new(3);
+        for _ in 0..200 {
+            let vars = 2 + rng.below(6);
+            let count = rng.below(2 * vars + 1);
+            let clauses = random_clauses(&mut rng, vars, count, 3);
+            let projection: Vec<usize> = (0..vars).filter(|_| rng.chance(0.5)).collect();
+            if projection.is_empty() {
+                continue;
+            }
+            let mut expected: Vec<Vec<bool>> = models(vars, &clauses).iter()
+                .map(|values| projection.iter().map(|&var| values[var]).collect())
+                .collect();
+            expected.sort();
+            expected.dedup();
+            let mut solver = solver_with(vars, &clauses);
+            let mut found = Vec::new();
+            enumerate(&mut solver, &projection, |model| {
+                assert!(model.iter().map(|lit| lit.var()).eq(projection.iter().cloned()));
+                found.push(values(model));
+            });
+            found.sort();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn stops_at_a_limit() {
+        let mut solver = solver_with(12, &[]);
+        solver.decision_limit(30);
+        let (count, complete) = enumerate(&mut solver, &[], |_| {});
+        assert!(!complete);
+        assert!(count < 1 << 12);
+    }
+}
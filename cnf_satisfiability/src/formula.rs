@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use card::Sink;
+use dimacs::Cnf;
+use solver::Lit;
+
+/// A propositional formula over named variables.
+#[derive(Clone, Debug)]
+pub enum Formula {
+    Const(bool),
+    Var(usize),
+    Not(Box<Formula>),
+    And(Box<Formula>, Box<Formula>),
+    Or(Box<Formula>, Box<Formula>),
+    Implies(Box<Formula>, Box<Formula>),
+    Iff(Box<Formula>, Box<Formula>),
+    Xor(Box<Formula>, Box<Formula>),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Token {
+    Ident(String),
+    Not,
+    And,
+    Or,
+    Xor,
+    Implies,
+    Iff,
+    True,
+    False,
+    Open,
+    Close,
+}
+
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            tokens.push(match word.as_str() {
+                "not" => Token::Not,
+                "and" => Token::And,
+                "or" => Token::Or,
+                "xor" => Token::Xor,
+                "implies" => Token::Implies,
+                "iff" => Token::Iff,
+                "true" => Token::True,
+                "false" => Token::False,
+                _ => Token::Ident(word),
+            });
+            continue;
+        }
+
+        let rest: String = chars[i..].iter().take(3).collect();
+        let (token, len) = if rest.starts_with("<->") || rest.starts_with("<=>") {
+            (Token::Iff, 3)
+        } else if rest.starts_with("->") || rest.starts_with("=>") {
+            (Token::Implies, 2)
+        } else if rest.starts_with("&&") {
+            (Token::And, 2)
+        } else if rest.starts_with("||") {
+            (Token::Or, 2)
+        } else {
+            match c {
+                '!' | '~' | '-' => (Token::Not, 1),
+                '&' => (Token::And, 1),
+                '|' => (Token::Or, 1),
+                '^' => (Token::Xor, 1),
+                '(' => (Token::Open, 1),
+                ')' => (Token::Close, 1),
+                _ => return Err(format!("Unexpected character {}", c)),
+            }
+        };
+        tokens.push(token);
+        i += len;
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over one formula. Binding gets tighter from `iff`
+/// through `implies`, `or`, `xor` and `and` down to `not`; `implies` groups
+/// to the right, the rest to the left.
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    names: &'a mut Vec<String>,
+    index: &'a mut HashMap<String, usize>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, token: Token) -> bool {
+        if self.peek() == Some(&token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn iff(&mut self) -> Result<Formula, String> {
+        let mut left = self.implies()?;
+        while self.eat(Token::Iff) {
+            let right = self.implies()?;
+            left = Formula::Iff(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn implies(&mut self) -> Result<Formula, String> {
+        let left = self.or()?;
+        if self.eat(Token::Implies) {
+            let right = self.implies()?;
+            return Ok(Formula::Implies(Box::new(left), Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Formula, String> {
+        let mut left = self.xor()?;
+        while self.eat(Token::Or) {
+            let right = self.xor()?;
+            left = Formula::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn xor(&mut self) -> Result<Formula, String> {
+        let mut left = self.and()?;
+        while self.eat(Token::Xor) {
+            let right = self.and()?;
+            left = Formula::Xor(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Formula, String> {
+        let mut left = self.unary()?;
+        while self.eat(Token::And) {
+            let right = self.unary()?;
+            left = Formula::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Formula, String> {
+        if self.eat(Token::Not) {
+            return Ok(Formula::Not(Box::new(self.unary()?)));
+        }
+        let token = match self.peek() {
+            Some(token) => token.clone(),
+            None => return Err(String::from("Unexpected end of formula")),
+        };
+        self.pos += 1;
+        match token {
+            Token::True => Ok(Formula::Const(true)),
+            Token::False => Ok(Formula::Const(false)),
+            Token::Ident(name) => {
+                let var = match self.index.get(&name) {
+                    Some(&var) => var,
+                    None => {
+                        self.names.push(name.clone());
+                        self.index.insert(name, self.names.len() - 1);
+                        self.names.len() - 1
+                    },
+                };
+                Ok(Formula::Var(var))
+            },
+            Token::Open => {
+                let inner = self.iff()?;
+                if !self.eat(Token::Close) {
+                    return Err(String::from("Missing )"));
+                }
+                Ok(inner)
+            },
+            other => Err(format!("Unexpected {:?}", other)),
+        }
+    }
+}
+
+/// Reads one formula per line; lines starting with `#` are comments. The
+/// input is the conjunction of all formulas. Returns the variable names, in
+/// order of first appearance, and the formulas over them.
+pub fn parse<R: BufRead>(input: R) -> Result<(Vec<String>, Vec<Formula>), String> {
+    let mut names = Vec::new();
+    let mut index = HashMap::new();
+    let mut formulas = Vec::new();
+
+    for line in input.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parser = Parser {
+            tokens: tokenize(line)?,
+            pos: 0,
+            names: &mut names,
+            index: &mut index,
+        };
+        let formula = parser.iff()?;
+        if parser.pos != parser.tokens.len() {
+            return Err(format!("Trailing input in {}", line));
+        }
+        formulas.push(formula);
+    }
+    Ok((names, formulas))
+}
+
+/// Tseitin transformation: gives every connective a fresh variable defined
+/// to be equivalent to it and returns the literal standing for `formula`.
+pub fn tseitin<S: Sink>(formula: &Formula, sink: &mut S) -> Lit {
+    match *formula {
+        Formula::Const(val) => {
+            let lit = Lit::new(sink.new_var(), false);
+            sink.add_clause(&[if val { lit } else { !lit }]);
+            lit
+        },
+        Formula::Var(var) => Lit::new(var, false),
+        Formula::Not(ref inner) => !tseitin(inner, sink),
+        Formula::And(ref a, ref b) => {
+            let (a, b) = (tseitin(a, sink), tseitin(b, sink));
+            let gate = Lit::new(sink.new_var(), false);
+            sink.add_clause(&[!gate, a]);
+            sink.add_clause(&[!gate, b]);
+            sink.add_clause(&[gate, !a, !b]);
+            gate
+        },
+        Formula::Or(ref a, ref b) => {
+            let (a, b) = (tseitin(a, sink), tseitin(b, sink));
+            or_gate(sink, a, b)
+        },
+        Formula::Implies(ref a, ref b) => {
+            let (a, b) = (tseitin(a, sink), tseitin(b, sink));
+            or_gate(sink, !a, b)
+        },
+        Formula::Iff(ref a, ref b) => {
+            let (a, b) = (tseitin(a, sink), tseitin(b, sink));
+            !xor_gate(sink, a, b)
+        },
+        Formula::Xor(ref a, ref b) => {
+            let (a, b) = (tseitin(a, sink), tseitin(b, sink));
+            xor_gate(sink, a, b)
+        },
+    }
+}
+
+fn or_gate<S: Sink>(sink: &mut S, a: Lit, b: Lit) -> Lit {
+    let gate = Lit::new(sink.new_var(), false);
+    sink.add_clause(&[gate, !a]);
+    sink.add_clause(&[gate, !b]);
+    sink.add_clause(&[!gate, a, b]);
+    gate
+}
+
+fn xor_gate<S: Sink>(sink: &mut S, a: Lit, b: Lit) -> Lit {
+    let gate = Lit::new(sink.new_var(), false);
+    sink.add_clause(&[!gate, a, b]);
+    sink.add_clause(&[!gate, !a, !b]);
+    sink.add_clause(&[gate, !a, b]);
+    sink.add_clause(&[gate, a, !b]);
+    gate
+}
+
+/// Converts the conjunction of `formulas` over `vars` named variables into
+/// an equisatisfiable CNF. Variables `0..vars` keep their meaning; the
+/// gate variables come after them. The first clauses are the unit clauses
+/// asserting each formula, in order, followed by the gate definitions.
+pub fn to_cnf(vars: usize, formulas: &[Formula]) -> Cnf {
+    let mut cnf = Cnf {
+        vars,
+        clauses: Vec::new(),
+        constraints: Vec::new(),
+        xors: Vec::new(),
+    };
+    let roots: Vec<Vec<Lit>> = formulas.iter()
+        .map(|formula| vec![tseitin(formula, &mut cnf)])
+        .collect();
+    let definitions = std::mem::replace(&mut cnf.clauses, roots);
+    cnf.clauses.extend(definitions);
+    cnf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rng::Rng;
+    use testing::{assignments, solver_with};
+
+    fn eval(formula: &Formula, values: &[bool]) -> bool {
+        match *formula {
+            Formula::Const(val) => val,
+            Formula::Var(var) => values[var],
+            Formula::Not(ref inner) => !eval(inner, values),
+            Formula::And(ref a, ref b) => eval(a, values) && eval(b, values),
+            Formula::Or(ref a, ref b) => eval(a, values) || eval(b, values),
+            Formula::Implies(ref a, ref b) => !eval(a, values) || eval(b, values),
+            Formula::Iff(ref a, ref b) => eval(a, values) == eval(b, values),
+            Formula::Xor(ref a, ref b) => eval(a, values) != eval(b, values),
+        }
+    }
+
+    fn random_formula(rng: &mut Rng, vars: usize, depth: usize) -> Formula {
+        if depth == 0 || rng.chance(0.2) {
+            return if rng.chance(0.1) { Formula::Const(rng.chance(0.5)) } else { Formula::Var(rng.below(vars)) };
+        }
+        let a = Box::new(random_formula(rng, vars, depth - 1));
+        let b = Box::new(random_formula(rng, vars, depth - 1));
+        match rng.below(6) {
+            0 => Formula::Not(a),
+            1 => Formula::And(a, b),
+            2 => Formula::Or(a, b),
+            3 => Formula::Implies(a, b),
+            4 => Formula::Iff(a, b),
+            _ => Formula::Xor(a, b),
+        }
+    }
+
+    #[test]
+    fn tseitin_keeps_the_models_of_the_inputs() {
+        let mut rng = Rng::new(1);
+        for _ in 0..200 {
+            let vars = 1 + rng.below(5);
+            let formulas: Vec<Formula> = (0..1 + rng.below(3)).map(|_| random_formula(&mut rng, vars, 4)).collect();
+            let cnf = to_cnf(vars, &formulas);
+            let mut solver = solver_with(cnf.vars, &cnf.clauses);
+            for values in assignments(vars) {
+                let fixed: Vec<Lit> = values.iter().enumerate().map(|(var, &value)| Lit::new(var, !value)).collect();
+                let expected = formulas.iter().all(|formula| eval(formula, &values));
+                assert_eq!(solver.solve_with_assumptions(&fixed), expected);
+            }
+        }
+    }
+
+    /// The truth table of a one-line formula over its variables, in order
+    /// of first appearance.
+    fn table(line: &str) -> Vec<bool> {
+        let (names, formulas) = parse(line.as_bytes()).unwrap();
+        assignments(names.len()).iter().map(|values| eval(&formulas[0], values)).collect()
+    }
+
+    #[test]
+    fn parses_precedence_and_grouping() {
+        assert_eq!(table("a | b & c"), table("a | (b & c)"));
+        assert_eq!(table("a ^ b & c"), table("a ^ (b & c)"));
+        assert_eq!(table("a | b ^ c"), table("a | (b ^ c)"));
+        assert_eq!(table("a -> b -> c"), table("a -> (b -> c)"));
+        assert_eq!(table("a => b | c <=> d"), table("(a -> (b || c)) <-> d"));
+        assert_eq!(table("!a & b"), table("(not a) and b"));
+        assert_eq!(table("~a || true"), table("a | -a"));
+        assert_ne!(table("a -> b -> c"), table("(a -> b) -> c"));
+    }
+
+    #[test]
+    fn names_variables_in_order_and_skips_comments() {
+        let input = "# ignored\n\nsecond_x -> y1\ny1 & z\n";
+        let (names, formulas) = parse(input.as_bytes()).unwrap();
+        assert_eq!(names, vec!["second_x", "y1", "z"]);
+        assert_eq!(formulas.len(), 2);
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        for line in ["a & (b", "a $ b", "a b", "a &", ")"].iter() {
+            assert!(parse(line.as_bytes()).is_err(), "{}", line);
+        }
+    }
+}
@@ -0,0 +1,389 @@
+use solver::Lit;
+
+/// What the rows touched since the last look imply. Implied literals come
+/// without their explanation, which `reason` builds only if conflict
+/// analysis asks for it; a conflict is the clause the assignment falsifies.
+pub enum Deduction {
+    Nothing,
+    Propagate(Vec<Lit>),
+    Conflict(Vec<Lit>),
+}
+
+/// XOR constraints kept as a matrix over GF(2), one bit per variable that
+/// occurs in some constraint, in reduced row echelon form over the columns
+/// of unassigned variables. Each row is solved for one unassigned column,
+/// its basic column, that no other row contains. Assigning a basic variable
+/// moves its row to another unassigned column, and unassigning one gives a
+/// fully assigned row a basic column again, so the matrix is never rebuilt
+/// during search. A row is unit once its basic column is the only
+/// unassigned one left.
+pub struct Gauss {
+    vars: Vec<usize>,
+    columns: Vec<Option<usize>>,
+    rows: Vec<Vec<u64>>,
+    rhs: Vec<bool>,
+    /// Basic column of each row, None while every variable in it is assigned.
+    basic: Vec<Option<usize>>,
+    /// Row each column is basic in.
+    basic_row: Vec<Option<usize>>,
+    /// Columns assigned so far, and which of them are true.
+    assigned: Vec<u64>,
+    values: Vec<u64>,
+    /// Rows to look at in the next `deduce`.
+    dirty: Vec<bool>,
+    queue: Vec<usize>,
+    /// The row each implied column was unit in, as it was at the time.
+    reasons: Vec<Vec<u64>>,
+    /// Constraints were added since the rows were last eliminated.
+    stale: bool,
+}
+
+fn bit(row: &[u64], col: usize) -> bool {
+    row[col / 64] >> (col % 64) & 1 == 1
+}
+
+/// Columns set in `row`.
+fn columns(row: &[u64]) -> impl Iterator<Item = usize> + '_ {
+    row.iter().enumerate().flat_map(|(w, &word)| {
+        (0..64).filter(move |&b| word >> b & 1 == 1).map(move |b| w * 64 + b)
+    })
+}
+
+impl Gauss {
+    pub fn new() -> Gauss {
+        Gauss {
+            vars: Vec::new(),
+            columns: Vec::new(),
+            rows: Vec::new(),
+            rhs: Vec::new(),
+            basic: Vec::new(),
+            basic_row: Vec::new(),
+            assigned: Vec::new(),
+            values: Vec::new(),
+            dirty: Vec::new(),
+            queue: Vec::new(),
+            reasons: Vec::new(),
+            stale: false,
+        }
+    }
+
+    fn words(&self) -> usize {
+        self.vars.len().div_ceil(64)
+    }
+
+    /// Adds the constraint that an odd number of `vars` is true when `rhs`
+    /// holds, an even number otherwise. Variables listed twice cancel out.
+    /// Forgets the assignment, which the caller has to hand over again.
+    pub fn add(&mut self, vars: &[usize], rhs: bool) {
+        for &var in vars.iter() {
+            if self.columns.len() <= var {
+                self.columns.resize(var + 1, None);
+            }
+            if self.columns[var].is_none() {
+                self.columns[var] = Some(self.vars.len());
+                self.vars.push(var);
+            }
+        }
+        let words = self.words();
+        for row in self.rows.iter_mut() {
+            row.resize(words, 0);
+        }
+        let mut row = vec![0u64; words];
+        for &var in vars.iter() {
+            let col = self.columns[var].unwrap();
+            row[col / 64] ^= 1 << (col % 64);
+        }
+        self.rows.push(row);
+        self.rhs.push(rhs);
+        self.stale = true;
+    }
+
+    /// Gauss-Jordan elimination of every row from scratch, with nothing
+    /// assigned.
+    fn eliminate(&mut self) {
+        let words = self.words();
+        self.assigned = vec![0; words];
+        self.values = vec![0; words];
+        self.basic = vec![None; self.rows.len()];
+        self.basic_row = vec![None; self.vars.len()];
+        self.dirty = vec![false; self.rows.len()];
+        self.queue.clear();
+        self.reasons.resize(self.vars.len(), Vec::new());
+        for r in 0..self.rows.len() {
+            self.settle(r);
+        }
+        self.stale = false;
+    }
+
+    fn touch(&mut self, r: usize) {
+        if !self.dirty[r] {
+            self.dirty[r] = true;
+            self.queue.push(r);
+        }
+    }
+
+    /// Solves row `r`, which has no basic column, for one of its unassigned
+    /// columns and removes that column from every other row.
+    fn settle(&mut self, r: usize) {
+        self.touch(r);
+        let free = self.rows[r].iter().zip(self.assigned.iter()).position(|(&row, &set)| row & !set != 0);
+        let col = match free {
+            Some(w) => w * 64 + (self.rows[r][w] & !self.assigned[w]).trailing_zeros() as usize,
+            None => return,
+        };
+        self.basic[r] = Some(col);
+        self.basic_row[col] = Some(r);
+        let (pivot, rhs) = (self.rows[r].clone(), self.rhs[r]);
+        for other in 0..self.rows.len() {
+            if other != r && bit(&self.rows[other], col) {
+                for (word, &p) in self.rows[other].iter_mut().zip(pivot.iter()) {
+                    *word ^= p;
+                }
+                self.rhs[other] ^= rhs;
+                self.touch(other);
+            }
+        }
+    }
+
+    /// Takes note that `var` now has `value`.
+    pub fn assign(&mut self, var: usize, value: bool) {
+        if self.stale {
+            self.eliminate();
+        }
+        let col = match self.columns.get(var) {
+            Some(&Some(col)) => col,
+            _ => return,
+        };
+        self.assigned[col / 64] |= 1 << (col % 64);
+        if value {
+            self.values[col / 64] |= 1 << (col % 64);
+        }
+        for r in 0..self.rows.len() {
+            if bit(&self.rows[r], col) {
+                self.touch(r);
+            }
+        }
+        if let Some(r) = self.basic_row[col].take() {
+            self.basic[r] = None;
+            self.settle(r);
+        }
+    }
+
+    /// Takes note that `var` is unassigned again, as on backtracking.
+    pub fn unassign(&mut self, var: usize) {
+        let col = match self.columns.get(var) {
+            Some(&Some(col)) if !self.stale => col,
+            _ => return,
+        };
+        self.assigned[col / 64] &= !(1 << (col % 64));
+        self.values[col / 64] &= !(1 << (col % 64));
+        for r in 0..self.rows.len() {
+            if bit(&self.rows[r], col) {
+                self.touch(r);
+                if self.basic[r].is_none() {
+                    self.settle(r);
+                }
+            }
+        }
+    }
+
+    /// Looks at the rows touched since the last call. Every unit row
+    /// implies its basic variable; a fully assigned row with the wrong
+    /// parity is a conflict, and the rows not looked at yet stay queued.
+    pub fn deduce(&mut self) -> Deduction {
+        if self.stale {
+            self.eliminate();
+        }
+        let mut implied = Vec::new();
+        let mut units = Vec::new();
+        while let Some(r) = self.queue.pop() {
+            self.dirty[r] = false;
+            let row = &self.rows[r];
+            let mut unassigned = 0;
+            let mut parity = self.rhs[r];
+            for (w, &word) in row.iter().enumerate() {
+                unassigned += (word & !self.assigned[w]).count_ones();
+                parity ^= (word & self.values[w]).count_ones() % 2 == 1;
+            }
+            match unassigned {
+                0 if parity => {
+                    let conflict = self.falsified(row, None);
+                    // What the unit rows imply still holds after backjumping
+                    // above them.
+                    for r in units.into_iter().chain(Some(r)) {
+                        self.touch(r);
+                    }
+                    return Deduction::Conflict(conflict);
+                },
+                1 => {
+                    let col = self.basic[r].unwrap();
+                    self.reasons[col] = row.clone();
+                    implied.push(Lit::new(self.vars[col], !parity));
+                    units.push(r);
+                },
+                _ => {},
+            }
+        }
+
+        if implied.is_empty() {
+            Deduction::Nothing
+        } else {
+            Deduction::Propagate(implied)
+        }
+    }
+
+    /// The literals of `row` other than `skip`'s that the current assignment
+    /// makes false.
+    fn falsified(&self, row: &[u64], skip: Option<usize>) -> Vec<Lit> {
+        columns(row)
+            .filter(|&col| Some(col) != skip)
+            .map(|col| Lit::new(self.vars[col], bit(&self.values, col)))
+            .collect()
+    }
+
+    /// Explanation of an implied `lit`: the clause of the row it was unit
+    /// in, with `lit` first and every other literal false.
+    pub fn reason(&self, lit: Lit) -> Vec<Lit> {
+        let col = self.columns[lit.var()].unwrap();
+        let mut clause = vec![lit];
+        clause.extend(self.falsified(&self.reasons[col], Some(col)));
+        clause
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rng::Rng;
+    use testing::{assignments, holds, models, random_clauses, satisfies, solver_with};
+
+    fn parity(xor: &[Lit], values: &[bool]) -> bool {
+        xor.iter().filter(|&&lit| holds(lit, values)).count() % 2 == 1
+    }
+
+    fn random_xors(rng: &mut Rng, vars: usize, count: usize) -> Vec<Vec<Lit>> {
+        (0..count)
+            .map(|_| (0..1 + rng.below(4)).map(|_| Lit::new(rng.below(vars), rng.chance(0.5))).collect())
+            .collect()
+    }
+
+    #[test]
+    fn solver_with_xors_matches_brute_force() {
+        let mut rng = Rng::new(1);
+        for _ in 0..400 {
+            let vars = 1 + rng.below(10);
+            let count = rng.below(3 * vars);
+            let clauses = random_clauses(&mut rng, vars, count, 3);
+            let count = 1 + rng.below(vars);
+            let xors = random_xors(&mut rng, vars, count);
+            let mut solver = solver_with(vars, &clauses);
+            for xor in xors.iter() {
+                solver.add_xor(xor);
+            }
+            let expected = models(vars, &clauses).iter().any(|values| xors.iter().all(|xor| parity(xor, values)));
+            assert_eq!(solver.solve(), expected);
+            if expected {
+                let values: Vec<bool> = solver.model().iter().map(|lit| !lit.is_negated()).collect();
+                assert!(satisfies(&values, &clauses));
+                assert!(xors.iter().all(|xor| parity(xor, &values)));
+            }
+        }
+    }
+
+    /// Asserts that `lit` follows from `xors` under `assigns` and that its
+    /// explanation is implied and false apart from `lit`.
+    fn check_reason(gauss: &Gauss, lit: Lit, assigns: &[Option<bool>], solutions: &[Vec<bool>]) {
+        let clause = gauss.reason(lit);
+        assert_eq!(clause[0], lit);
+        assert!(clause[1..].iter().all(|other| assigns[other.var()] == Some(other.is_negated())));
+        assert!(solutions.iter().all(|values| clause.iter().any(|&lit| holds(lit, values))));
+    }
+
+    #[test]
+    fn incremental_elimination_matches_brute_force() {
+        let mut rng = Rng::new(2);
+        for _ in 0..300 {
+            let vars = 1 + rng.below(8);
+            let count = 1 + rng.below(vars + 2);
+            let xors = random_xors(&mut rng, vars, count);
+            let mut gauss = Gauss::new();
+            for xor in xors.iter() {
+                let rhs = xor.iter().fold(true, |rhs, lit| rhs ^ lit.is_negated());
+                gauss.add(&xor.iter().map(|lit| lit.var()).collect::<Vec<_>>(), rhs);
+            }
+            let solutions: Vec<Vec<bool>> = assignments(vars).into_iter()
+                .filter(|values| xors.iter().all(|xor| parity(xor, values)))
+                .collect();
+
+            // Decisions and their consequences, undone from the top.
+            let mut assigns: Vec<Option<bool>> = vec![None; vars];
+            let mut trail: Vec<usize> = Vec::new();
+            for _ in 0..20 {
+                if !trail.is_empty() && rng.chance(0.3) {
+                    let keep = rng.below(trail.len());
+                    for var in trail.drain(keep..).rev() {
+                        gauss.unassign(var);
+                        assigns[var] = None;
+                    }
+                } else if let Some(var) = (0..vars).find(|&var| assigns[var].is_none()) {
+                    let value = rng.chance(0.5);
+                    gauss.assign(var, value);
+                    assigns[var] = Some(value);
+                    trail.push(var);
+                }
+
+                let conflict = loop {
+                    match gauss.deduce() {
+                        Deduction::Nothing => break false,
+                        Deduction::Conflict(clause) => {
+                            assert!(clause.iter().all(|lit| assigns[lit.var()] == Some(lit.is_negated())));
+                            assert!(solutions.iter().all(|values| clause.iter().any(|&lit| holds(lit, values))));
+                            break true;
+                        },
+                        Deduction::Propagate(lits) => {
+                            for lit in lits {
+                                assert_eq!(assigns[lit.var()], None);
+                                check_reason(&gauss, lit, &assigns, &solutions);
+                                gauss.assign(lit.var(), !lit.is_negated());
+                                assigns[lit.var()] = Some(!lit.is_negated());
+                                trail.push(lit.var());
+                            }
+                        },
+                    }
+                };
+                let consistent: Vec<&Vec<bool>> = solutions.iter()
+                    .filter(|values| (0..vars).all(|var| assigns[var].is_none_or(|val| values[var] == val)))
+                    .collect();
+                assert_eq!(conflict, consistent.is_empty());
+                // Nothing left unassigned is fixed by the constraints.
+                if !conflict {
+                    for var in (0..vars).filter(|&var| assigns[var].is_none()) {
+                        assert!(consistent.iter().any(|values| values[var]) && consistent.iter().any(|values| !values[var]));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rows_span_several_words() {
+        // x0 ^ ... ^ x130 odd and every x_i equal to x_{i+1}: all true, and
+        // unsatisfiable over an even number of variables.
+        let mut solver = solver_with(131, &[]);
+        solver.add_xor(&(0..131).map(|var| Lit::new(var, false)).collect::<Vec<_>>());
+        for var in 0..130 {
+            solver.add_xor(&[Lit::new(var, false), Lit::new(var + 1, true)]);
+        }
+        assert!(solver.solve());
+        assert!(solver.model().iter().all(|lit| !lit.is_negated()));
+        solver.add_clause(&[Lit::new(75, true)]);
+        assert!(!solver.solve());
+
+        let mut solver = solver_with(130, &[]);
+        solver.add_xor(&(0..130).map(|var| Lit::new(var, false)).collect::<Vec<_>>());
+        for var in 0..129 {
+            solver.add_xor(&[Lit::new(var, false), Lit::new(var + 1, true)]);
+        }
+        assert!(!solver.solve());
+    }
+}
@@ -0,0 +1,17 @@
+pub mod bignum;
+pub mod card;
+pub mod core;
+pub mod count;
+pub mod dimacs;
+pub mod enumerate;
+pub mod formula;
+mod gauss;
+pub mod maxsat;
+pub mod portfolio;
+pub mod preprocess;
+pub mod rng;
+pub mod solver;
+#[cfg(test)]
+mod testing;
+
+pub use solver::{Lit, Solver, Stats};
@@ -1,178 +1,393 @@
-use std::io::BufRead;
+extern crate cue6_05;
 
-static mut BRANCHES: usize = 0;
+use cue6_05::{dimacs, Lit, Solver};
+use cue6_05::card::Encoding;
+use cue6_05::core::unsat_core;
+use cue6_05::count::Counter;
+use cue6_05::enumerate::enumerate;
+use cue6_05::formula;
+use cue6_05::maxsat::MaxSat;
+use cue6_05::portfolio;
+use cue6_05::preprocess::Preprocessor;
 
-fn read_preamble() -> Option<(usize, usize)> {
-    let stdin = std::io::stdin();
-    let mut line = String::new();
-
-    while let Ok(size) = stdin.lock().read_line(&mut line) {
-        let buff = line.clone();
-        line.clear();
-        if size == 0 {
-            // Got EOF without falling into p directive
-            break;
-        }
-
-        let words: Vec<&str> = buff.split_whitespace().collect();
-        match words.get(0) {
-            Some(&"c") => continue,
-            Some(&"p") => {
-                match words.get(1) {
-                    Some(&"cnf") => {},
-                    _ => {
-                        println!("error with p directive");
-                        continue
-                    },
-                };
-                let (vars, clauses) = match (words.get(2), words.get(3)) {
-                    (Some(vars), Some(clauses)) => (vars, clauses),
-                    _ => {
-                        println!("error with p directive");
-                        continue
-                    },
-                };
+use std::env;
+use std::fs::File;
+use std::time::{Duration, Instant};
 
-                let vars_num = vars.parse::<usize>().unwrap();
-                let clauses_num = clauses.parse::<usize>().unwrap();
-                return Some((vars_num, clauses_num));
-            },
-            Some(x) => println!("Unknown {}", x),
-            None => println!("Input error"),
-        }
-    }
-    None
+enum Mode {
+    Solve,
+    Enumerate,
+    Count,
+    MaxSat,
 }
 
-/// Takes utf8 from stdin until a 0.
-fn read_clause() -> Option<String> {
-    let stdin = std::io::stdin();
-    let mut buff = Vec::new();
-    match stdin.lock().read_until(b'0', &mut buff) {
-        Ok(0) => return None,
-        Ok(_) => {},
-        Err(_) => return None,
+struct Options {
+    mode: Mode,
+    projection: Vec<usize>,
+    print_models: bool,
+    preprocess: bool,
+    core: bool,
+    core_file: Option<String>,
+    minimize: bool,
+    threads: usize,
+    encoding: Encoding,
+    formula: bool,
+    max_conflicts: Option<usize>,
+    max_decisions: Option<usize>,
+    timeout: Option<Duration>,
+    stats: bool,
+}
+
+fn usage() {
+    println!("Usage: cue6_05 [OPTIONS] < FILE.cnf
+                      Options: --enumerate     List all models using blocking clauses
+                               --count         Exact model count by component caching
+                               --maxsat        Read WCNF and minimize the falsified soft weight
+                               --project VARS  Comma separated variables to enumerate over
+                               --models        Print each model when enumerating or counting
+                               --preprocess    Simplify the formula before solving
+                               --formula       Read one propositional formula per line instead of DIMACS
+                               --core          On UNSAT, print the indices of an unsatisfiable core
+                               --core-cnf FILE On UNSAT, write the core to FILE as DIMACS
+                               --minimize      Shrink the core until every clause is needed
+                               --threads N     Race N differently configured solvers
+                               --card-encoding seqcounter|totalizer
+                                               CNF translation of k and w constraints
+                               --max-conflicts N
+                               --max-decisions N
+                               --timeout SECS  Give up with an unknown answer past these limits
+                               --stats         Print search statistics")
+}
+
+fn parse_args() -> Option<Options> {
+    let mut options = Options {
+        mode: Mode::Solve,
+        projection: Vec::new(),
+        print_models: false,
+        preprocess: false,
+        core: false,
+        core_file: None,
+        minimize: false,
+        threads: 1,
+        encoding: Encoding::Totalizer,
+        formula: false,
+        max_conflicts: None,
+        max_decisions: None,
+        timeout: None,
+        stats: false,
     };
-    buff.pop(); // Drop ending 0
 
-    // Ensure consuming beyond numbers with 0, i.e. 10, 20
-    while !buff.ends_with(&[b' ']) {
-        buff.push(b'0');
-        let new_buff = read_clause().unwrap();
-        let other = new_buff.as_bytes();
-        for i in 0..other.len() {
-            buff.push(other[i]);
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--enumerate" => options.mode = Mode::Enumerate,
+            "--count" => options.mode = Mode::Count,
+            "--maxsat" => options.mode = Mode::MaxSat,
+            "--models" => options.print_models = true,
+            "--preprocess" => options.preprocess = true,
+            "--formula" => options.formula = true,
+            "--core" => options.core = true,
+            "--core-cnf" => {
+                options.core = true;
+                options.core_file = Some(args.next()?);
+            },
+            "--minimize" => options.minimize = true,
+            "--card-encoding" => {
+                options.encoding = match args.next()?.as_str() {
+                    "seqcounter" => Encoding::SequentialCounter,
+                    "totalizer" => Encoding::Totalizer,
+                    _ => return None,
+                };
+            },
+            "--max-conflicts" => options.max_conflicts = Some(args.next()?.parse().ok()?),
+            "--max-decisions" => options.max_decisions = Some(args.next()?.parse().ok()?),
+            "--timeout" => {
+                let secs = args.next()?.parse::<f64>().ok()?;
+                if secs < 0.0 {
+                    return None;
+                }
+                options.timeout = Some(Duration::from_secs_f64(secs));
+            },
+            "--stats" => options.stats = true,
+            "--threads" => {
+                match args.next()?.parse::<usize>() {
+                    Ok(threads) if threads > 0 => options.threads = threads,
+                    _ => return None,
+                }
+            },
+            "--project" => {
+                let vars = args.next()?;
+                for var in vars.split(',') {
+                    match var.parse::<usize>() {
+                        Ok(var) if var > 0 => options.projection.push(var - 1),
+                        _ => return None,
+                    }
+                }
+            },
+            _ => return None,
         }
     }
-
-    match std::str::from_utf8(&buff) {
-        Ok(s) => Some(String::from(s)),
-        _ => None
+    // Counting covers every variable.
+    if let (Mode::Count, false) = (&options.mode, options.projection.is_empty()) {
+        return None;
     }
+    Some(options)
 }
 
-/// Converts a string into Boolean logic.
-fn gen_clause(line: String) -> Vec<isize> {
-    let mut clause = Vec::new();
-    for number in line.split_whitespace() {
-        let num = number.parse::<isize>().unwrap();
-        clause.push(num);
+/// Shows a literal by its DIMACS number, or by its name when the input
+/// named its variables.
+fn show(lit: Lit, names: &[String]) -> String {
+    match names.get(lit.var()) {
+        Some(name) if lit.is_negated() => format!("-{}", name),
+        Some(name) => name.clone(),
+        None => lit.to_dimacs().to_string(),
     }
-    clause
 }
 
+fn print_model(model: &[Lit], names: &[String]) {
+    let lits: Vec<String> = model.iter().map(|&lit| show(lit, names)).collect();
+    println!("v {} 0", lits.join(" "));
+}
 
-fn set_variable(clauses: &mut Vec<Vec<isize>>, val: isize) {
-    clauses.retain(|x| !x.contains(&val));
-    for clause in clauses.iter_mut() {
-        clause.retain(|&x| x != (val * -1));
+/// Reports a core among the first `original` clauses of `cnf`; the rest
+/// encode its linear constraints and are treated as background.
+fn print_core(cnf: &dimacs::Cnf, original: usize, options: &Options) {
+    if !cnf.xors.is_empty() {
+        println!("c cores are not computed for formulas with x constraints");
+        return;
+    }
+    let (clauses, background) = cnf.clauses.split_at(original);
+    let core = match unsat_core(cnf.vars, clauses, background, options.minimize) {
+        Some(core) => core,
+        None => return,
+    };
+    match options.core_file {
+        Some(ref path) => {
+            let clauses: Vec<Vec<Lit>> = core.iter().map(|&i| cnf.clauses[i].clone()).collect();
+            let written = File::create(path)
+                .and_then(|mut file| dimacs::write(&mut file, cnf.vars, &clauses));
+            if let Err(e) = written {
+                println!("Could not write {}: {}", path, e);
+            }
+        },
+        None => {
+            for i in core {
+                println!("u {}", i + 1);
+            }
+        },
     }
 }
 
-fn unit_val(clauses: &Vec<Vec<isize>>) -> isize {
-    for clause in clauses.iter() {
-        if clause.len() == 1 {
-            return *(clause.get(0).unwrap());
+/// Returns a function that puts the limits from the command line on a solver.
+fn limits(options: &Options) -> impl Fn(&mut Solver) + Send + Sync + 'static {
+    let (conflicts, decisions, timeout) = (options.max_conflicts, options.max_decisions, options.timeout);
+    move |solver: &mut Solver| {
+        if let Some(conflicts) = conflicts {
+            solver.conflict_limit(conflicts);
+        }
+        if let Some(decisions) = decisions {
+            solver.decision_limit(decisions);
+        }
+        if let Some(timeout) = timeout {
+            solver.time_limit(timeout);
         }
     }
-    0
 }
 
-fn unit_propagate(clauses: &mut Vec<Vec<isize>>, solution: &mut Vec<isize>) {
-    //println!("propagateing...");
-    let item = unit_val(clauses);
-    if item == 0 {
-        //println!("No units");
-        return;
-    } else {
-        unsafe { BRANCHES += 1; }
-        //println!("pushing {}", item);
-        solution.push(item);
-        set_variable(clauses, item);
+fn load(vars: usize, clauses: &[Vec<Lit>], xors: &[Vec<Lit>]) -> Solver {
+    let mut solver = Solver::new();
+    while solver.num_vars() < vars {
+        solver.new_var();
+    }
+    for clause in clauses.iter() {
+        solver.add_clause(clause);
+    }
+    for xor in xors.iter() {
+        solver.add_xor(xor);
     }
-    unit_propagate(clauses, solution);
+    solver
 }
 
-fn dll(vars_num: usize, index: isize, clauses: &mut Vec<Vec<isize>>, solution: &mut Vec<isize>) -> bool {
-    //println!("Iter {:?}", clauses);
-    unsafe { BRANCHES += 1; }
-    unit_propagate(clauses, solution);
-    //println!("{:?}", clauses);
-    if clauses.len() == 0 {
-        return true;
+fn main() {
+    let options = match parse_args() {
+        Some(options) => options,
+        None => { usage(); return },
+    };
+
+    let stdin = std::io::stdin();
+    if let Mode::MaxSat = options.mode {
+        let wcnf = match dimacs::parse_wcnf(stdin.lock()) {
+            Ok(wcnf) => wcnf,
+            Err(e) => { println!("Input error: {}", e); return },
+        };
+        let mut maxsat = MaxSat::new(&wcnf);
+        limits(&options)(maxsat.solver_mut());
+        match maxsat.solve(|cost, _| println!("o {}", cost)) {
+            (Some((_, model)), complete) => {
+                // Past a limit the last model is only the best one found.
+                println!("s {}", if complete { "OPTIMUM FOUND" } else { "SATISFIABLE" });
+                print_model(&model, &[]);
+            },
+            (None, true) => println!("s UNSATISFIABLE"),
+            (None, false) => println!("s UNKNOWN"),
+        }
+        if options.stats {
+            println!("{}", maxsat.stats());
+        }
+        println!("{} branching nodes explored", maxsat.stats().decisions);
+        return;
+    }
+
+    let mut names = Vec::new();
+    let mut roots = 0;
+    let parsed = if options.formula {
+        formula::parse(stdin.lock()).map(|(vars, formulas)| {
+            let cnf = formula::to_cnf(vars.len(), &formulas);
+            names = vars;
+            roots = formulas.len();
+            cnf
+        })
+    } else {
+        dimacs::parse(stdin.lock())
+    };
+    let mut cnf = match parsed {
+        Ok(cnf) => cnf,
+        Err(e) => { println!("Input error: {}", e); return },
+    };
+    // Only these variables and clauses are reported back; anything after
+    // them was added by an encoding.
+    let (vars, original) = if options.formula {
+        (names.len(), roots)
     } else {
-        for clause in clauses.iter() {
-            if clause.len() == 0 {
-                solution.pop();
-                //println!("Empty clause");
-                return false;
+        (cnf.vars, cnf.clauses.len())
+    };
+
+    if let Mode::Count = options.mode {
+        if !cnf.constraints.is_empty() || !cnf.xors.is_empty() {
+            println!("Model counting does not support k, w and x constraints");
+            return;
+        }
+        if options.print_models {
+            // The counter never looks at single models, so they are listed
+            // by enumeration.
+            let mut solver = load(cnf.vars, &cnf.clauses, &cnf.xors);
+            limits(&options)(&mut solver);
+            let projection: Vec<usize> = (0..vars).collect();
+            let (_, complete) = enumerate(&mut solver, &projection, |model| print_model(model, &names));
+            if !complete {
+                println!("c stopped by a limit, not every model is listed");
             }
         }
+        let mut counter = Counter::new();
+        if let Some(conflicts) = options.max_conflicts {
+            counter.conflict_limit(conflicts);
+        }
+        if let Some(decisions) = options.max_decisions {
+            counter.decision_limit(decisions);
+        }
+        if let Some(timeout) = options.timeout {
+            counter.time_limit(timeout);
+        }
+        let start = Instant::now();
+        match counter.count(cnf.vars, &cnf.clauses) {
+            Some(count) => println!("s mc {}", count),
+            None => println!("s UNKNOWN"),
+        }
+        if options.stats {
+            println!("c decisions        {}", counter.decisions);
+            println!("c conflicts        {}", counter.conflicts);
+            println!("c cache hits       {}", counter.cache_hits);
+            println!("c time             {:.3} s", start.elapsed().as_secs_f64());
+        }
+        println!("{} branching nodes explored, {} cache hits", counter.decisions, counter.cache_hits);
+        return;
     }
 
-    let mut old_clauses = clauses.clone();
-    set_variable(clauses, index);
-    solution.push(index);
-    //println!("set true{:?}", solution);
-    if dll(vars_num, index + 1, clauses, solution) {
-        return true;
+    for linear in std::mem::take(&mut cnf.constraints) {
+        linear.encode(&mut cnf, options.encoding);
     }
 
-
-    solution.pop();
-    //println!("true failed{:?}", solution);
-    set_variable(&mut old_clauses, -index);
-    solution.push(-index);
-    //println!("set false{:?}", solution);
-    if dll(vars_num, index + 1, &mut old_clauses, solution) {
-        return true;
+    if let Mode::Enumerate = options.mode {
+        // Preprocessing would change the set of models, so it is skipped.
+        let mut solver = load(cnf.vars, &cnf.clauses, &cnf.xors);
+        limits(&options)(&mut solver);
+        let projection: Vec<usize> = if options.projection.is_empty() {
+            (0..vars).collect()
+        } else {
+            options.projection.clone()
+        };
+        let print_models = options.print_models;
+        let (models, complete) = enumerate(&mut solver, &projection, |model| {
+            if print_models {
+                print_model(model, &names);
+            }
+        });
+        if !complete {
+            println!("c stopped by a limit, the count is a lower bound");
+        }
+        println!("s mc {}", models);
+        if options.stats {
+            println!("{}", solver.stats());
+        }
+        println!("{} branching nodes explored", solver.stats().decisions);
+        return;
     }
 
-    //println!("false failed.");
-
-    solution.pop();
-    //println!("unset false{:?}", solution);
-    return false;
-}
+    let preprocessor = if options.preprocess {
+        let mut pre = Preprocessor::new(cnf.vars, &cnf.clauses);
+        for lit in cnf.xors.iter().flat_map(|xor| xor.iter()) {
+            pre.freeze(lit.var());
+        }
+        pre.simplify();
+        println!("c preprocessing removed {} clauses and {} variables",
+                 pre.removed_clauses(), pre.removed_vars());
+        Some(pre)
+    } else {
+        None
+    };
+    let clauses = match preprocessor {
+        Some(ref pre) => pre.clauses(),
+        None => cnf.clauses.clone(),
+    };
 
-fn main() {
-    let (vars_num, clauses_num) = match read_preamble() {
-        Some((v, c)) => (v, c),
-        None => { println!("Input error."); return },
+    let (solved, model, stats) = if options.threads > 1 {
+        let (limits, xors) = (limits(&options), cnf.xors.clone());
+        let answer = portfolio::solve(cnf.vars, clauses, options.threads, move |solver: &mut Solver| {
+            for xor in xors.iter() {
+                solver.add_xor(xor);
+            }
+            limits(solver);
+        });
+        if let Some(winner) = answer.winner {
+            println!("c solved by instance {}", winner);
+        }
+        (answer.sat, answer.model, answer.stats)
+    } else {
+        let mut solver = load(cnf.vars, &clauses, &cnf.xors);
+        limits(&options)(&mut solver);
+        let solved = solver.solve_limited(&[]);
+        (solved, solver.model(), solver.stats().clone())
     };
 
-    let mut clauses: Vec<Vec<isize>> = Vec::new();
-    for _ in 0..clauses_num {
-        clauses.push(gen_clause(read_clause().unwrap()));
+    match solved {
+        Some(sat) => println!("s cnf {} {} {}", sat as i32, vars, original),
+        None => println!("s UNKNOWN"),
     }
-
-    let mut solution = Vec::new();
-    let solved = dll(vars_num, 1, &mut clauses, &mut solution);
-    println!("s cnf {} {} {}", if solved { 1 } else { 0 }, vars_num, clauses_num);
-    if solved {
-        for sol in solution {
-            println!("v {}", sol);
+    if solved == Some(true) {
+        let mut model: Vec<bool> = (0..cnf.vars)
+            .map(|var| !model[var].is_negated())
+            .collect();
+        if let Some(ref pre) = preprocessor {
+            pre.extend_model(&mut model);
         }
+        for (var, &val) in model.iter().enumerate().take(vars) {
+            println!("v {}", show(Lit::new(var, !val), &names));
+        }
+    } else if solved == Some(false) && options.core {
+        print_core(&cnf, original, &options);
+    }
+    if options.stats {
+        println!("{}", stats);
     }
-    unsafe { println!("{} branching nodes explored", BRANCHES); }
+    println!("{} branching nodes explored", stats.decisions);
 }
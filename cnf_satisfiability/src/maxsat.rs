@@ -0,0 +1,182 @@
+use card::Totalizer;
+use dimacs::Wcnf;
+use solver::{Lit, Solver, Stats};
+
+/// Weighted partial MaxSAT by linear SAT-UNSAT search: every model found
+/// bounds the cost from above, and a totalizer over the relaxation literals
+/// then forbids anything that is not strictly cheaper, until the solver
+/// proves no cheaper assignment exists.
+pub struct MaxSat {
+    solver: Solver,
+    vars: usize,
+    soft: Vec<(u64, Vec<Lit>)>,
+    relax: Vec<(u64, Lit)>,
+}
+
+impl MaxSat {
+    pub fn new(wcnf: &Wcnf) -> MaxSat {
+        let mut solver = Solver::new();
+        while solver.num_vars() < wcnf.vars {
+            solver.new_var();
+        }
+        for clause in wcnf.hard.iter() {
+            solver.add_clause(clause);
+        }
+
+        let mut relax = Vec::with_capacity(wcnf.soft.len());
+        for &(weight, ref clause) in wcnf.soft.iter() {
+            if weight == 0 {
+                continue;
+            }
+            // A falsified unit clause is already its own relaxation literal.
+            let lit = if clause.len() == 1 {
+                !clause[0]
+            } else {
+                let lit = Lit::new(solver.new_var(), false);
+                let mut relaxed = clause.clone();
+                relaxed.push(lit);
+                solver.add_clause(&relaxed);
+                lit
+            };
+            relax.push((weight, lit));
+        }
+
+        MaxSat {
+            solver,
+            vars: wcnf.vars,
+            soft: wcnf.soft.clone(),
+            relax,
+        }
+    }
+
+    pub fn stats(&self) -> &Stats {
+        self.solver.stats()
+    }
+
+    /// The underlying solver, to put limits on.
+    pub fn solver_mut(&mut self) -> &mut Solver {
+        &mut self.solver
+    }
+
+    /// Finds an optimal assignment, reporting each improving cost and model
+    /// to `on_improve` as it goes. Returns the best assignment found, or
+    /// None if there is none, and whether the search finished. If the solver
+    /// runs out of one of its limits, the search stops early and the cost
+    /// found is only an upper bound.
+    pub fn solve<F>(&mut self, mut on_improve: F) -> (Option<(u64, Vec<Lit>)>, bool)
+        where F: FnMut(u64, &[Lit])
+    {
+        match self.solver.solve_limited(&[]) {
+            Some(true) => {},
+            Some(false) => return (None, true),
+            None => return (None, false),
+        }
+        let mut best = self.current();
+        on_improve(best.0, &best.1);
+        if best.0 == 0 {
+            return (Some(best), true);
+        }
+
+        let totalizer = Totalizer::new(&mut self.solver, &self.relax, best.0);
+        while totalizer.enforce_below(&mut self.solver, best.0) {
+            match self.solver.solve_limited(&[]) {
+                Some(true) => {},
+                Some(false) => break,
+                None => return (Some(best), false),
+            }
+            best = self.current();
+            on_improve(best.0, &best.1);
+            if best.0 == 0 {
+                break;
+            }
+        }
+        (Some(best), true)
+    }
+
+    /// Cost and model of the solver's last model, over the input variables.
+    fn current(&self) -> (u64, Vec<Lit>) {
+        let model: Vec<Lit> = (0..self.vars)
+            .map(|var| Lit::new(var, self.solver.model_value(Lit::new(var, false)) != Some(true)))
+            .collect();
+        let cost = self.soft.iter()
+            .filter(|&(_, clause)| {
+                !clause.iter().any(|&lit| self.solver.model_value(lit) == Some(true))
+            })
+            .map(|&(weight, _)| weight)
+            .sum();
+        (cost, model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rng::Rng;
+    use testing::{assignments, random_clauses, satisfies};
+
+    fn random_wcnf(rng: &mut Rng, vars: usize) -> Wcnf {
+        let hard = rng.below(2 * vars);
+        let soft = 1 + rng.below(3 * vars);
+        Wcnf {
+            vars,
+            hard: random_clauses(rng, vars, hard, 3),
+            soft: random_clauses(rng, vars, soft, 2).into_iter().map(|clause| (rng.below(5) as u64, clause)).collect(),
+        }
+    }
+
+    /// The least falsified soft weight over assignments meeting the hard
+    /// clauses, if there are any.
+    fn optimum(wcnf: &Wcnf) -> Option<u64> {
+        assignments(wcnf.vars).iter()
+            .filter(|values| satisfies(values, &wcnf.hard))
+            .map(|values| cost(wcnf, values))
+            .min()
+    }
+
+    fn cost(wcnf: &Wcnf, values: &[bool]) -> u64 {
+        wcnf.soft.iter()
+            .filter(|&(_, clause)| !satisfies(values, std::slice::from_ref(clause)))
+            .map(|&(weight, _)| weight)
+            .sum()
+    }
+
+    #[test]
+    fn optimum_matches_brute_force() {
+        let mut rng = Rng::new(5);
+        for _ in 0..300 {
+            let vars = 1 + rng.below(9);
+            let wcnf = random_wcnf(&mut rng, vars);
+            let mut costs = Vec::new();
+            let (best, complete) = MaxSat::new(&wcnf).solve(|cost, _| costs.push(cost));
+            assert!(complete);
+            assert_eq!(best.as_ref().map(|&(cost, _)| cost), optimum(&wcnf));
+            if let Some((best, model)) = best {
+                let values: Vec<bool> = model.iter().map(|lit| !lit.is_negated()).collect();
+                assert!(satisfies(&values, &wcnf.hard));
+                assert_eq!(cost(&wcnf, &values), best);
+                // Every reported cost improves on the one before.
+                assert!(costs.windows(2).all(|pair| pair[1] < pair[0]));
+                assert_eq!(costs.last(), Some(&best));
+            }
+        }
+    }
+
+    #[test]
+    fn stops_at_a_limit_with_the_best_cost_so_far() {
+        let mut rng = Rng::new(6);
+        let wcnf = random_wcnf(&mut rng, 40);
+        let mut maxsat = MaxSat::new(&wcnf);
+        maxsat.solver_mut().decision_limit(0);
+        assert_eq!(maxsat.solve(|_, _| panic!("no model within the limit")), (None, false));
+
+        let mut maxsat = MaxSat::new(&wcnf);
+        let (best, complete) = maxsat.solve(|_, _| {});
+        assert!(complete);
+        let optimum = best.unwrap().0;
+        let mut maxsat = MaxSat::new(&wcnf);
+        maxsat.solver_mut().conflict_limit(1);
+        if let (Some((cost, _)), false) = maxsat.solve(|_, _| {}) {
+            assert!(cost >= optimum);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use rng::Rng;
+use solver::{Exchange, Lit, Polarity, Restarts, Solver, Stats};
+
+/// Learned clauses up to this length are passed between the instances.
+const SHARE_LEN: usize = 8;
+
+/// Outcome of a portfolio run. `sat` and `winner` are None when every
+/// instance ran out of its limits; `stats` then belongs to instance 0.
+pub struct Answer {
+    pub sat: Option<bool>,
+    pub model: Vec<Lit>,
+    pub winner: Option<usize>,
+    pub stats: Stats,
+}
+
+/// Configures instance `id` of a portfolio. Instance 0 is the plain solver;
+/// the others vary the seed, decision polarity and restart policy.
+fn configure(solver: &mut Solver, id: usize) {
+    if id == 0 {
+        return;
+    }
+    let polarity = match id % 4 {
+        0 => Polarity::Saved,
+        1 => Polarity::Positive,
+        2 => Polarity::Random,
+        _ => Polarity::Negative,
+    };
+    let restarts = if id % 2 == 1 { Restarts::Geometric } else { Restarts::Luby };
+    solver.seed(id as u64)
+        .random_freq(0.01 * (id % 5) as f64)
+        .polarity(polarity)
+        .restarts(restarts);
+}
+
+/// Runs `threads` differently configured solvers on the same clauses. The
+/// first one to find an answer stops the others. `setup` is applied to
+/// every instance before it starts, e.g. to set limits.
+pub fn solve<F>(vars: usize, clauses: Vec<Vec<Lit>>, threads: usize, setup: F) -> Answer
+    where F: Fn(&mut Solver) + Send + Sync + 'static
+{
+    let clauses = Arc::new(clauses);
+    let setup = Arc::new(setup);
+    let stop = Arc::new(AtomicBool::new(false));
+    let exchange = Arc::new(Exchange::new(SHARE_LEN));
+    let answer: Arc<Mutex<Option<Answer>>> = Arc::new(Mutex::new(None));
+
+    let mut handles = Vec::with_capacity(threads);
+    for id in 0..threads {
+        let clauses = clauses.clone();
+        let setup = setup.clone();
+        let stop = stop.clone();
+        let exchange = exchange.clone();
+        let answer = answer.clone();
+        handles.push(thread::spawn(move || {
+            let mut solver = Solver::new();
+            configure(&mut solver, id);
+            setup(&mut solver);
+            solver.interrupt(stop.clone()).exchange(exchange, id);
+            while solver.num_vars() < vars {
+                solver.new_var();
+            }
+            // Shuffle the activities a little so that equal configurations
+            // still branch differently.
+            if id > 0 {
+                let mut rng = Rng::new(id as u64);
+                for var in 0..vars {
+                    solver.bump(var, rng.next_f64() * 1e-3);
+                }
+            }
+            for clause in clauses.iter() {
+                solver.add_clause(clause);
+            }
+
+            let sat = solver.solve_limited(&[]);
+            let mut answer = answer.lock().unwrap();
+            let first = match *answer {
+                None => true,
+                Some(ref answer) => answer.sat.is_none() && (sat.is_some() || id == 0),
+            };
+            if first {
+                if sat.is_some() {
+                    stop.store(true, Ordering::Relaxed);
+                }
+                *answer = Some(Answer {
+                    sat,
+                    model: if sat == Some(true) { solver.model() } else { Vec::new() },
+                    winner: sat.map(|_| id),
+                    stats: solver.stats().clone(),
+                });
+            }
+        }));
+    }
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let mut answer = answer.lock().unwrap();
+    answer.take().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing::{models, random_clauses, satisfies, solver_with};
+
+    #[test]
+    fn every_configuration_answers_right() {
+        let mut rng = Rng::new(1);
+        for _ in 0..200 {
+            let vars = 1 + rng.below(10);
+            let count = rng.below(6 * vars);
+            let clauses = random_clauses(&mut rng, vars, count, 3);
+            let sat = !models(vars, &clauses).is_empty();
+            for id in 0..8 {
+                let mut solver = solver_with(vars, &[]);
+                configure(&mut solver, id);
+                for clause in clauses.iter() {
+                    solver.add_clause(clause);
+                }
+                assert_eq!(solver.solve(), sat);
+            }
+        }
+    }
+
+    #[test]
+    fn racing_instances_agree_with_brute_force() {
+        let mut rng = Rng::new(2);
+        for _ in 0..50 {
+            let vars = 1 + rng.below(10);
+            let count = rng.below(6 * vars);
+            let clauses = random_clauses(&mut rng, vars, count, 3);
+            let answer = solve(vars, clauses.clone(), 1 + rng.below(4), |_: &mut Solver| {});
+            let expected = !models(vars, &clauses).is_empty();
+            assert_eq!(answer.sat, Some(expected));
+            assert!(answer.winner.is_some());
+            if expected {
+                let values: Vec<bool> = answer.model.iter().map(|lit| !lit.is_negated()).collect();
+                assert!(satisfies(&values, &clauses));
+            }
+        }
+    }
+
+    /// Eight pigeons in seven holes, which takes enough conflicts for the
+    /// instances to share clauses.
+    fn pigeonhole() -> Vec<Vec<Lit>> {
+        let mut clauses: Vec<Vec<Lit>> = (0..8).map(|p| (0..7).map(|h| Lit::new(7 * p + h, false)).collect()).collect();
+        for h in 0..7 {
+            for p in 0..8 {
+                for q in p + 1..8 {
+                    clauses.push(vec![Lit::new(7 * p + h, true), Lit::new(7 * q + h, true)]);
+                }
+            }
+        }
+        clauses
+    }
+
+    #[test]
+    fn shared_clauses_keep_refutations_sound() {
+        let answer = solve(56, pigeonhole(), 4, |_: &mut Solver| {});
+        assert_eq!(answer.sat, Some(false));
+    }
+
+    #[test]
+    fn reports_no_winner_when_every_instance_stops() {
+        let answer = solve(56, pigeonhole(), 3, |solver: &mut Solver| { solver.conflict_limit(10); });
+        assert_eq!((answer.sat, answer.winner), (None, None));
+        assert!(answer.stats.conflicts >= 10);
+    }
+}
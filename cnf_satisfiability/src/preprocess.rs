@@ -0,0 +1,371 @@
+use std::collections::VecDeque;
+
+use solver::Lit;
+
+/// Resolvent sets are only tried when one side is this small or the product
+/// of both sides stays below `MAX_PRODUCT`.
+const MAX_SIDE: usize = 10;
+const MAX_PRODUCT: usize = 100;
+const MAX_RESOLVENT: usize = 20;
+
+/// Outcome of comparing two clauses for subsumption.
+enum Subsumes {
+    No,
+    Yes,
+    /// The first clause subsumes the second once this literal is dropped
+    /// from the second one (self-subsuming resolution).
+    Strengthen(Lit),
+}
+
+/// Simplifies a clause set before search: pure literal elimination,
+/// subsumption, self-subsuming resolution and bounded variable elimination.
+/// Every removed clause is kept on a stack so that a model of the simplified
+/// formula can be extended to one of the original.
+pub struct Preprocessor {
+    vars: usize,
+    clauses: Vec<Vec<Lit>>,
+    removed: Vec<bool>,
+    occurs: Vec<Vec<usize>>,
+    eliminated: Vec<bool>,
+    frozen: Vec<bool>,
+    stack: Vec<(Lit, Vec<Lit>)>,
+    queue: VecDeque<usize>,
+    unsat: bool,
+    original_clauses: usize,
+    original_vars: usize,
+}
+
+impl Preprocessor {
+    pub fn new(vars: usize, clauses: &[Vec<Lit>]) -> Preprocessor {
+        let mut pre = Preprocessor {
+            vars,
+            clauses: Vec::with_capacity(clauses.len()),
+            removed: Vec::with_capacity(clauses.len()),
+            occurs: vec![Vec::new(); vars],
+            eliminated: vec![false; vars],
+            frozen: vec![false; vars],
+            stack: Vec::new(),
+            queue: VecDeque::new(),
+            unsat: false,
+            original_clauses: clauses.len(),
+            original_vars: 0,
+        };
+        for clause in clauses {
+            pre.add(clause.clone());
+        }
+        pre.original_vars = pre.occurs.iter().filter(|occ| !occ.is_empty()).count();
+        pre
+    }
+
+    /// Keeps `var` out of pure literal and variable elimination, for
+    /// variables that constraints outside the clause set still mention.
+    pub fn freeze(&mut self, var: usize) {
+        self.frozen[var] = true;
+    }
+
+    /// Runs the simplifications until none of them makes progress.
+    pub fn simplify(&mut self) {
+        let mut changed = true;
+        while changed && !self.unsat {
+            changed = self.eliminate_pure();
+            changed |= self.subsume();
+            changed |= self.eliminate_vars();
+        }
+    }
+
+    /// The simplified clause set. Contains the empty clause if
+    /// simplification found the formula unsatisfiable.
+    pub fn clauses(&self) -> Vec<Vec<Lit>> {
+        if self.unsat {
+            return vec![Vec::new()];
+        }
+        (0..self.clauses.len())
+            .filter(|&cr| !self.removed[cr])
+            .map(|cr| self.clauses[cr].clone())
+            .collect()
+    }
+
+    pub fn removed_clauses(&self) -> usize {
+        self.original_clauses.saturating_sub(self.clauses().len())
+    }
+
+    pub fn removed_vars(&self) -> usize {
+        let remaining = (0..self.vars)
+            .filter(|&var| self.occurs[var].iter().any(|&cr| !self.removed[cr]))
+            .count();
+        self.original_vars - remaining
+    }
+
+    /// Turns a model of the simplified clauses into a model of the original
+    /// ones by replaying the removed clauses in reverse.
+    pub fn extend_model(&self, model: &mut [bool]) {
+        for &(witness, ref clause) in self.stack.iter().rev() {
+            let satisfied = clause.iter().any(|lit| model[lit.var()] != lit.is_negated());
+            if !satisfied {
+                model[witness.var()] = !witness.is_negated();
+            }
+        }
+    }
+
+    fn add(&mut self, mut clause: Vec<Lit>) {
+        clause.sort();
+        clause.dedup();
+        if clause.windows(2).any(|pair| pair[1] == !pair[0]) {
+            return;
+        }
+        if clause.is_empty() {
+            self.unsat = true;
+            return;
+        }
+        let cr = self.clauses.len();
+        for lit in clause.iter() {
+            self.occurs[lit.var()].push(cr);
+        }
+        self.clauses.push(clause);
+        self.removed.push(false);
+        self.queue.push_back(cr);
+    }
+
+    /// Drops a clause, remembering `witness` for model reconstruction.
+    fn remove(&mut self, cr: usize, witness: Option<Lit>) {
+        self.removed[cr] = true;
+        if let Some(witness) = witness {
+            self.stack.push((witness, self.clauses[cr].clone()));
+        }
+    }
+
+    /// Live clauses containing `var`, split by the sign it appears with.
+    fn occurrences(&mut self, var: usize) -> (Vec<usize>, Vec<usize>) {
+        let removed = &self.removed;
+        self.occurs[var].retain(|&cr| !removed[cr]);
+        let mut pos = Vec::new();
+        let mut neg = Vec::new();
+        for &cr in self.occurs[var].iter() {
+            if self.clauses[cr].contains(&Lit::new(var, false)) {
+                pos.push(cr);
+            } else {
+                neg.push(cr);
+            }
+        }
+        (pos, neg)
+    }
+
+    fn eliminate_pure(&mut self) -> bool {
+        let mut changed = false;
+        for var in 0..self.vars {
+            if self.eliminated[var] || self.frozen[var] {
+                continue;
+            }
+            let (pos, neg) = self.occurrences(var);
+            let (pure, negated) = match (pos.is_empty(), neg.is_empty()) {
+                (false, true) => (pos, false),
+                (true, false) => (neg, true),
+                _ => continue,
+            };
+            for cr in pure {
+                self.remove(cr, Some(Lit::new(var, negated)));
+            }
+            self.eliminated[var] = true;
+            changed = true;
+        }
+        changed
+    }
+
+    /// Backward subsumption and self-subsuming resolution from every clause
+    /// in the queue.
+    fn subsume(&mut self) -> bool {
+        let mut changed = false;
+        while let Some(cr) = self.queue.pop_front() {
+            if self.removed[cr] {
+                continue;
+            }
+            // Both kinds of partner clause contain the rarest variable of cr.
+            let var = self.clauses[cr].iter()
+                .map(|lit| lit.var())
+                .min_by_key(|&var| self.occurs[var].len())
+                .unwrap();
+            let candidates = self.occurs[var].clone();
+            for other in candidates {
+                if other == cr || self.removed[other] || self.removed[cr] {
+                    continue;
+                }
+                match subsumes(&self.clauses[cr], &self.clauses[other]) {
+                    Subsumes::No => {},
+                    Subsumes::Yes => {
+                        self.remove(other, None);
+                        changed = true;
+                    },
+                    Subsumes::Strengthen(lit) => {
+                        self.strengthen(other, lit);
+                        changed = true;
+                        if self.unsat {
+                            return true;
+                        }
+                    },
+                }
+            }
+        }
+        changed
+    }
+
+    fn strengthen(&mut self, cr: usize, lit: Lit) {
+        self.clauses[cr].retain(|&l| l != lit);
+        self.occurs[lit.var()].retain(|&c| c != cr);
+        if self.clauses[cr].is_empty() {
+            self.unsat = true;
+        }
+        self.queue.push_back(cr);
+    }
+
+    /// Eliminates variables whose resolvents do not outnumber the clauses
+    /// they replace.
+    fn eliminate_vars(&mut self) -> bool {
+        let mut changed = false;
+        for var in 0..self.vars {
+            if self.eliminated[var] || self.frozen[var] || self.unsat {
+                continue;
+            }
+            let (pos, neg) = self.occurrences(var);
+            if pos.is_empty() || neg.is_empty() {
+                continue;
+            }
+            if pos.len() > MAX_SIDE && neg.len() > MAX_SIDE
+                || pos.len() * neg.len() > MAX_PRODUCT {
+                continue;
+            }
+
+            let mut resolvents = Vec::new();
+            let mut bounded = true;
+            'outer: for &p in pos.iter() {
+                for &n in neg.iter() {
+                    if let Some(resolvent) = resolve(&self.clauses[p], &self.clauses[n], var) {
+                        if resolvent.len() > MAX_RESOLVENT
+                            || resolvents.len() == pos.len() + neg.len() {
+                            bounded = false;
+                            break 'outer;
+                        }
+                        resolvents.push(resolvent);
+                    }
+                }
+            }
+            if !bounded {
+                continue;
+            }
+
+            for cr in pos {
+                self.remove(cr, Some(Lit::new(var, false)));
+            }
+            for cr in neg {
+                self.remove(cr, Some(Lit::new(var, true)));
+            }
+            for resolvent in resolvents {
+                self.add(resolvent);
+            }
+            self.eliminated[var] = true;
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// Compares two sorted clauses.
+fn subsumes(c: &[Lit], d: &[Lit]) -> Subsumes {
+    if c.len() > d.len() {
+        return Subsumes::No;
+    }
+    let mut result = Subsumes::Yes;
+    for &lit in c.iter() {
+        if d.binary_search(&lit).is_ok() {
+            continue;
+        }
+        match result {
+            Subsumes::Yes if d.binary_search(&!lit).is_ok() => {
+                result = Subsumes::Strengthen(!lit);
+            },
+            _ => return Subsumes::No,
+        }
+    }
+    result
+}
+
+/// Resolves two clauses on `var`, or None if the resolvent is a tautology.
+fn resolve(pos: &[Lit], neg: &[Lit], var: usize) -> Option<Vec<Lit>> {
+    let mut resolvent: Vec<Lit> = pos.iter()
+        .chain(neg.iter())
+        .cloned()
+        .filter(|lit| lit.var() != var)
+        .collect();
+    resolvent.sort();
+    resolvent.dedup();
+    if resolvent.windows(2).any(|pair| pair[1] == !pair[0]) {
+        None
+    } else {
+        Some(resolvent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rng::Rng;
+    use testing::{models, random_clauses, satisfies};
+
+    #[test]
+    fn simplified_models_extend_to_original_ones() {
+        let mut rng = Rng::new(1);
+        for _ in 0..500 {
+            let vars = 1 + rng.below(10);
+            let count = rng.below(5 * vars);
+            let clauses = random_clauses(&mut rng, vars, count, 3);
+            let mut pre = Preprocessor::new(vars, &clauses);
+            pre.simplify();
+            let simplified = models(vars, &pre.clauses());
+            assert_eq!(simplified.is_empty(), models(vars, &clauses).is_empty());
+            for mut model in simplified {
+                pre.extend_model(&mut model);
+                assert!(satisfies(&model, &clauses));
+            }
+            assert!(pre.removed_vars() <= vars);
+        }
+    }
+
+    #[test]
+    fn frozen_variables_keep_their_models() {
+        let mut rng = Rng::new(2);
+        for _ in 0..300 {
+            let vars = 2 + rng.below(8);
+            let count = rng.below(4 * vars);
+            let clauses = random_clauses(&mut rng, vars, count, 3);
+            let frozen: Vec<usize> = (0..vars).filter(|_| rng.chance(0.4)).collect();
+            let mut pre = Preprocessor::new(vars, &clauses);
+            for &var in frozen.iter() {
+                pre.freeze(var);
+            }
+            pre.simplify();
+            let project = |models: Vec<Vec<bool>>| {
+                let mut projected: Vec<Vec<bool>> = models.iter()
+                    .map(|values| frozen.iter().map(|&var| values[var]).collect())
+                    .collect();
+                projected.sort();
+                projected.dedup();
+                projected
+            };
+            assert_eq!(project(models(vars, &pre.clauses())), project(models(vars, &clauses)));
+        }
+    }
+
+    #[test]
+    fn pure_literals_take_their_clauses() {
+        // Every literal of (a or b), (a or b or c), (-c or d) is pure, so
+        // nothing is left, and the all-false assignment is repaired.
+        let lit = |num| Lit::from_dimacs(num);
+        let clauses = vec![vec![lit(1), lit(2)], vec![lit(1), lit(2), lit(3)], vec![lit(-3), lit(4)]];
+        let mut pre = Preprocessor::new(4, &clauses);
+        pre.simplify();
+        assert!(pre.clauses().is_empty());
+        assert_eq!((pre.removed_clauses(), pre.removed_vars()), (3, 4));
+        let mut model = vec![false; 4];
+        pre.extend_model(&mut model);
+        assert!(satisfies(&model, &clauses));
+    }
+}
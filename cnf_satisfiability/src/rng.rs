@@ -0,0 +1,66 @@
+/// Small xorshift* generator. Runs are reproducible from the seed alone,
+/// which is all the solver, local search and benchmark generators need.
+///
+/// graph_coloring compiles this same file as its own `rng` module, so both
+/// crates draw the same streams from the same seeds.
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // The state must never be zero; mix the seed so nearby seeds diverge.
+        let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ 0xD1B5_4A32_D192_ED03;
+        if state == 0 {
+            state = 1;
+        }
+        Rng(state)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform integer in `0..bound`.
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// Uniform float in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rng;
+
+    #[test]
+    fn seeds_reproduce_their_stream() {
+        let (mut a, mut b, mut c) = (Rng::new(7), Rng::new(7), Rng::new(8));
+        let first: Vec<u64> = (0..20).map(|_| a.next_u64()).collect();
+        assert!(first.iter().all(|&x| x == b.next_u64()));
+        assert!(first.iter().any(|&x| x != c.next_u64()));
+        assert_ne!(Rng::new(0).next_u64(), 0);
+    }
+
+    #[test]
+    fn draws_stay_in_range() {
+        let mut rng = Rng::new(1);
+        let mut seen = [false; 6];
+        for _ in 0..1000 {
+            seen[rng.below(6)] = true;
+            let x = rng.next_f64();
+            assert!((0.0..1.0).contains(&x));
+        }
+        assert!(seen.iter().all(|&seen| seen));
+        assert!(!rng.chance(0.0) && rng.chance(1.0));
+    }
+}
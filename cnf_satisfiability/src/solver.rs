@@ -0,0 +1,1413 @@
+use std::cmp;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::mem;
+use std::ops::Not;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use gauss::{Deduction, Gauss};
+use rng::Rng;
+
+/// A propositional literal: a variable together with a polarity.
+///
+/// Variables are numbered from 0 inside the solver. The DIMACS conversions
+/// shift them by one so that `Lit::from_dimacs(1)` is the first variable.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Lit(u32);
+
+impl Lit {
+    pub fn new(var: usize, negated: bool) -> Lit {
+        Lit((var as u32) << 1 | negated as u32)
+    }
+
+    /// Builds a literal from a nonzero DIMACS integer.
+    pub fn from_dimacs(num: isize) -> Lit {
+        assert!(num != 0, "0 is not a DIMACS literal");
+        Lit::new(num.unsigned_abs() - 1, num < 0)
+    }
+
+    pub fn to_dimacs(self) -> isize {
+        let var = self.var() as isize + 1;
+        if self.is_negated() { -var } else { var }
+    }
+
+    pub fn var(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    pub fn is_negated(self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+/// Which value a decision gives its variable.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Polarity {
+    /// The value the variable last had before it was unassigned.
+    Saved,
+    Negative,
+    Positive,
+    Random,
+}
+
+/// How many conflicts each run between restarts may take.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Restarts {
+    /// 100 times the Luby sequence.
+    Luby,
+    /// 100 conflicts, growing by half after every restart.
+    Geometric,
+}
+
+/// Pool of learned clauses that solvers working on the same formula hand to
+/// each other. Only clauses of at most `max_len` literals are shared, and a
+/// clause is dropped once every member has read it.
+pub struct Exchange {
+    log: Mutex<Log>,
+    max_len: usize,
+}
+
+/// The clauses some member of an exchange has not read yet.
+struct Log {
+    /// Position in the whole history of the first clause still kept.
+    start: usize,
+    clauses: VecDeque<(usize, Vec<Lit>)>,
+    /// How far each member has read, by id; None if it is not a member.
+    cursors: Vec<Option<usize>>,
+}
+
+impl Log {
+    /// Forgets the clauses every member has read.
+    fn trim(&mut self) {
+        let end = self.start + self.clauses.len();
+        let read = self.cursors.iter().filter_map(|&cursor| cursor).min().unwrap_or(end);
+        while self.start < read {
+            self.clauses.pop_front();
+            self.start += 1;
+        }
+    }
+}
+
+impl Exchange {
+    pub fn new(max_len: usize) -> Exchange {
+        Exchange {
+            log: Mutex::new(Log { start: 0, clauses: VecDeque::new(), cursors: Vec::new() }),
+            max_len,
+        }
+    }
+
+    /// Makes `id` a member that reads every clause still kept.
+    fn join(&self, id: usize) {
+        let mut log = self.log.lock().unwrap();
+        if log.cursors.len() <= id {
+            log.cursors.resize(id + 1, None);
+        }
+        log.cursors[id] = Some(log.start);
+    }
+
+    fn leave(&self, id: usize) {
+        let mut log = self.log.lock().unwrap();
+        if let Some(cursor) = log.cursors.get_mut(id) {
+            *cursor = None;
+        }
+        log.trim();
+    }
+
+    fn publish(&self, id: usize, clause: &[Lit]) {
+        self.log.lock().unwrap().clauses.push_back((id, clause.to_vec()));
+    }
+
+    /// The clauses other members published since `id` last read.
+    fn read(&self, id: usize) -> Vec<Vec<Lit>> {
+        let mut log = self.log.lock().unwrap();
+        let end = log.start + log.clauses.len();
+        let cursor = match log.cursors.get(id) {
+            Some(&Some(cursor)) => cursor,
+            _ => return Vec::new(),
+        };
+        let incoming = log.clauses.iter()
+            .skip(cursor - log.start)
+            .filter(|&&(from, _)| from != id)
+            .map(|(_, clause)| clause.clone())
+            .collect();
+        log.cursors[id] = Some(end);
+        log.trim();
+        incoming
+    }
+}
+
+/// Counters kept by a `Solver` across all of its `solve` calls.
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    pub solves: usize,
+    pub decisions: usize,
+    pub propagations: usize,
+    pub conflicts: usize,
+    pub learned: usize,
+    pub restarts: usize,
+    /// Bytes held by stored clauses and their watches, now and at most.
+    pub clause_bytes: usize,
+    pub peak_clause_bytes: usize,
+    /// Time spent inside `solve` calls.
+    pub time: Duration,
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "c decisions        {}", self.decisions)?;
+        writeln!(f, "c propagations     {}", self.propagations)?;
+        writeln!(f, "c conflicts        {}", self.conflicts)?;
+        writeln!(f, "c learned clauses  {}", self.learned)?;
+        writeln!(f, "c restarts         {}", self.restarts)?;
+        writeln!(f, "c peak clause db   {} bytes", self.peak_clause_bytes)?;
+        write!(f, "c time             {:.3} s", self.time.as_secs_f64())
+    }
+}
+
+/// Entry of a watch list. The blocker is some other literal of the clause;
+/// while it is true the clause can be skipped without being looked at.
+#[derive(Clone, Copy)]
+struct Watcher {
+    cr: usize,
+    blocker: Lit,
+}
+
+struct Clause {
+    lits: Vec<Lit>,
+    learnt: bool,
+    activity: f64,
+}
+
+/// Binary max-heap over variables keyed by their VSIDS activity.
+struct VarOrder {
+    heap: Vec<usize>,
+    indices: Vec<Option<usize>>,
+}
+
+impl VarOrder {
+    fn new() -> VarOrder {
+        VarOrder {
+            heap: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    fn contains(&self, var: usize) -> bool {
+        self.indices[var].is_some()
+    }
+
+    fn insert(&mut self, var: usize, activity: &[f64]) {
+        while self.indices.len() <= var {
+            self.indices.push(None);
+        }
+        if self.contains(var) {
+            return;
+        }
+        self.indices[var] = Some(self.heap.len());
+        self.heap.push(var);
+        let i = self.heap.len() - 1;
+        self.sift_up(i, activity);
+    }
+
+    /// Restores the heap after the activity of `var` grew.
+    fn increased(&mut self, var: usize, activity: &[f64]) {
+        if let Some(i) = self.indices[var] {
+            self.sift_up(i, activity);
+        }
+    }
+
+    fn pop(&mut self, activity: &[f64]) -> Option<usize> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        let top = self.heap.swap_remove(0);
+        self.indices[top] = None;
+        if !self.heap.is_empty() {
+            self.indices[self.heap[0]] = Some(0);
+            self.sift_down(0, activity);
+        }
+        Some(top)
+    }
+
+    fn sift_up(&mut self, mut i: usize, activity: &[f64]) {
+        let var = self.heap[i];
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if activity[self.heap[parent]] >= activity[var] {
+                break;
+            }
+            self.heap[i] = self.heap[parent];
+            self.indices[self.heap[i]] = Some(i);
+            i = parent;
+        }
+        self.heap[i] = var;
+        self.indices[var] = Some(i);
+    }
+
+    fn sift_down(&mut self, mut i: usize, activity: &[f64]) {
+        let var = self.heap[i];
+        loop {
+            let left = 2 * i + 1;
+            if left >= self.heap.len() {
+                break;
+            }
+            let right = left + 1;
+            let child = if right < self.heap.len()
+                && activity[self.heap[right]] > activity[self.heap[left]] {
+                right
+            } else {
+                left
+            };
+            if activity[self.heap[child]] <= activity[var] {
+                break;
+            }
+            self.heap[i] = self.heap[child];
+            self.indices[self.heap[i]] = Some(i);
+            i = child;
+        }
+        self.heap[i] = var;
+        self.indices[var] = Some(i);
+    }
+}
+
+/// Reason of a literal implied by the XOR constraints, whose clause is only
+/// built if conflict analysis needs it.
+const XOR_REASON: usize = usize::MAX;
+
+fn lit_value(assigns: &[Option<bool>], lit: Lit) -> Option<bool> {
+    assigns[lit.var()].map(|val| val != lit.is_negated())
+}
+
+/// Memory a stored clause of `len` literals takes, counting its two watches.
+fn footprint(len: usize) -> usize {
+    mem::size_of::<Clause>() + len * mem::size_of::<Lit>() + 2 * mem::size_of::<Watcher>()
+}
+
+/// Finite subsequences of the Luby restart sequence: 1 1 2 1 1 2 4 1 1 2 ...
+fn luby(mut x: usize) -> usize {
+    let mut size = 1;
+    let mut seq = 0;
+    while size < x + 1 {
+        seq += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != x {
+        size = (size - 1) >> 1;
+        seq -= 1;
+        x %= size;
+    }
+    1 << seq
+}
+
+/// Incremental CDCL SAT solver.
+///
+/// Clauses may be added between calls to `solve_with_assumptions`; learned
+/// clauses, variable activities and saved phases carry over, so a sequence of
+/// closely related queries gets cheaper as it goes.
+pub struct Solver {
+    clauses: Vec<Clause>,
+    free: Vec<usize>,
+    learnts: Vec<usize>,
+    watches: Vec<Vec<Watcher>>,
+    assigns: Vec<Option<bool>>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    trail: Vec<Lit>,
+    trail_lim: Vec<usize>,
+    qhead: usize,
+    activity: Vec<f64>,
+    var_inc: f64,
+    cla_inc: f64,
+    order: VarOrder,
+    phase: Vec<bool>,
+    seen: Vec<bool>,
+    max_learnts: f64,
+    learnt_adjust_confl: f64,
+    learnt_adjust_cnt: usize,
+    ok: bool,
+    assumptions: Vec<Lit>,
+    model: Vec<bool>,
+    failed: Vec<Lit>,
+    stats: Stats,
+    rng: Rng,
+    random_freq: f64,
+    polarity: Polarity,
+    restarts: Restarts,
+    interrupt: Option<Arc<AtomicBool>>,
+    exchange: Option<(Arc<Exchange>, usize)>,
+    conflict_budget: Option<usize>,
+    decision_budget: Option<usize>,
+    deadline: Option<Instant>,
+    xors: Option<Gauss>,
+    /// Trail position up to which the XOR constraints know the assignment.
+    xhead: usize,
+    /// Clauses built from XOR constraints for conflict analysis, with the
+    /// variable each one is the reason of. They go once that variable is
+    /// unassigned, or at the next backjump for a conflict.
+    xor_clauses: Vec<(Option<usize>, usize)>,
+}
+
+impl Default for Solver {
+    fn default() -> Solver {
+        Solver::new()
+    }
+}
+
+impl Drop for Solver {
+    /// Leaves the exchange, so the clauses kept for this solver can go.
+    fn drop(&mut self) {
+        if let Some((ref exchange, id)) = self.exchange {
+            exchange.leave(id);
+        }
+    }
+}
+
+impl Solver {
+    pub fn new() -> Solver {
+        Solver {
+            clauses: Vec::new(),
+            free: Vec::new(),
+            learnts: Vec::new(),
+            watches: Vec::new(),
+            assigns: Vec::new(),
+            level: Vec::new(),
+            reason: Vec::new(),
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
+            qhead: 0,
+            activity: Vec::new(),
+            var_inc: 1.0,
+            cla_inc: 1.0,
+            order: VarOrder::new(),
+            phase: Vec::new(),
+            seen: Vec::new(),
+            max_learnts: 0.0,
+            learnt_adjust_confl: 0.0,
+            learnt_adjust_cnt: 0,
+            ok: true,
+            assumptions: Vec::new(),
+            model: Vec::new(),
+            failed: Vec::new(),
+            stats: Stats::default(),
+            rng: Rng::new(0),
+            random_freq: 0.0,
+            polarity: Polarity::Saved,
+            restarts: Restarts::Luby,
+            interrupt: None,
+            exchange: None,
+            conflict_budget: None,
+            decision_budget: None,
+            deadline: None,
+            xors: None,
+            xhead: 0,
+            xor_clauses: Vec::new(),
+        }
+    }
+
+    /// Seeds the generator behind random decisions and random polarity.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.rng = Rng::new(seed);
+        self
+    }
+
+    /// Fraction of decisions made on a random variable instead of the most
+    /// active one.
+    pub fn random_freq(&mut self, freq: f64) -> &mut Self {
+        self.random_freq = freq;
+        self
+    }
+
+    pub fn polarity(&mut self, polarity: Polarity) -> &mut Self {
+        self.polarity = polarity;
+        self
+    }
+
+    pub fn restarts(&mut self, restarts: Restarts) -> &mut Self {
+        self.restarts = restarts;
+        self
+    }
+
+    /// Makes `solve_limited` give up once `flag` is set.
+    pub fn interrupt(&mut self, flag: Arc<AtomicBool>) -> &mut Self {
+        self.interrupt = Some(flag);
+        self
+    }
+
+    /// Lets `solve_limited` spend `conflicts` more conflicts from now on,
+    /// across all calls, before it gives up.
+    pub fn conflict_limit(&mut self, conflicts: usize) -> &mut Self {
+        self.conflict_budget = Some(self.stats.conflicts + conflicts);
+        self
+    }
+
+    /// Lets `solve_limited` make `decisions` more decisions from now on.
+    pub fn decision_limit(&mut self, decisions: usize) -> &mut Self {
+        self.decision_budget = Some(self.stats.decisions + decisions);
+        self
+    }
+
+    /// Makes `solve_limited` give up once `time` has passed from now.
+    pub fn time_limit(&mut self, time: Duration) -> &mut Self {
+        self.deadline = Some(Instant::now() + time);
+        self
+    }
+
+    /// Joins a clause exchange under the name `id`. Short learned clauses
+    /// are published to it and those of the other members are imported at
+    /// every restart.
+    pub fn exchange(&mut self, exchange: Arc<Exchange>, id: usize) -> &mut Self {
+        if let Some((ref old, old_id)) = self.exchange {
+            old.leave(old_id);
+        }
+        exchange.join(id);
+        self.exchange = Some((exchange, id));
+        self
+    }
+
+    /// Creates a fresh variable and returns its index.
+    pub fn new_var(&mut self) -> usize {
+        let var = self.assigns.len();
+        self.assigns.push(None);
+        self.level.push(0);
+        self.reason.push(None);
+        self.activity.push(0.0);
+        self.phase.push(false);
+        self.seen.push(false);
+        self.watches.push(Vec::new());
+        self.watches.push(Vec::new());
+        self.order.insert(var, &self.activity);
+        var
+    }
+
+    /// Raises the branching activity of `var` by `amount`.
+    pub fn bump(&mut self, var: usize, amount: f64) {
+        self.activity[var] += amount;
+        self.order.increased(var, &self.activity);
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.assigns.len()
+    }
+
+    /// Number of problem clauses currently stored, excluding learned ones
+    /// and units that were folded into the top-level assignment.
+    pub fn num_clauses(&self) -> usize {
+        self.clauses.len() - self.free.len() - self.learnts.len()
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Adds a clause, creating any variables it mentions. Returns false once
+    /// the clause set is known to be unsatisfiable without assumptions.
+    pub fn add_clause(&mut self, lits: &[Lit]) -> bool {
+        self.add_root_clause(lits, false)
+    }
+
+    /// Adds the constraint that an odd number of `lits` is true. XORs are
+    /// kept apart from the clauses and reasoned about by Gauss-Jordan
+    /// elimination during search.
+    pub fn add_xor(&mut self, lits: &[Lit]) -> bool {
+        self.cancel_until(0);
+        if !self.ok {
+            return false;
+        }
+        // A negated literal flips the parity of its variable.
+        let mut rhs = true;
+        let mut vars = Vec::with_capacity(lits.len());
+        for lit in lits {
+            while lit.var() >= self.num_vars() {
+                self.new_var();
+            }
+            rhs ^= lit.is_negated();
+            vars.push(lit.var());
+        }
+        vars.sort();
+        let mut kept: Vec<usize> = Vec::with_capacity(vars.len());
+        for var in vars {
+            if kept.last() == Some(&var) {
+                kept.pop();
+            } else {
+                kept.push(var);
+            }
+        }
+        self.xors.get_or_insert_with(Gauss::new).add(&kept, rhs);
+        self.xhead = 0;
+        true
+    }
+
+    fn add_root_clause(&mut self, lits: &[Lit], learnt: bool) -> bool {
+        self.cancel_until(0);
+        if !self.ok {
+            return false;
+        }
+        for lit in lits {
+            while lit.var() >= self.num_vars() {
+                self.new_var();
+            }
+        }
+
+        let mut clause = lits.to_vec();
+        clause.sort();
+        clause.dedup();
+        let mut kept = Vec::with_capacity(clause.len());
+        for (i, &lit) in clause.iter().enumerate() {
+            if i + 1 < clause.len() && clause[i + 1] == !lit {
+                // Tautology
+                return true;
+            }
+            match lit_value(&self.assigns, lit) {
+                Some(true) => return true,
+                Some(false) => {},
+                None => kept.push(lit),
+            }
+        }
+
+        match kept.len() {
+            0 => self.ok = false,
+            1 => {
+                self.enqueue(kept[0], None);
+                self.ok = self.propagate().is_none();
+            },
+            _ => { self.attach(kept, learnt); },
+        }
+        self.ok
+    }
+
+    pub fn solve(&mut self) -> bool {
+        self.solve_with_assumptions(&[])
+    }
+
+    /// Searches for a model in which every literal of `assumptions` holds.
+    ///
+    /// On failure, `failed_assumptions` holds the subset of assumptions that
+    /// was used to refute the query. An empty subset means the clauses are
+    /// unsatisfiable on their own.
+    pub fn solve_with_assumptions(&mut self, assumptions: &[Lit]) -> bool {
+        self.solve_limited(assumptions) == Some(true)
+    }
+
+    /// Like `solve_with_assumptions`, but returns None if the search was
+    /// interrupted or ran out of its conflict, decision or time limit before
+    /// it found an answer.
+    pub fn solve_limited(&mut self, assumptions: &[Lit]) -> Option<bool> {
+        let start = Instant::now();
+        let result = self.search(assumptions);
+        self.stats.time += start.elapsed();
+        result
+    }
+
+    fn search(&mut self, assumptions: &[Lit]) -> Option<bool> {
+        self.model.clear();
+        self.failed.clear();
+        self.stats.solves += 1;
+        if !self.import_shared() {
+            return Some(false);
+        }
+        for lit in assumptions {
+            while lit.var() >= self.num_vars() {
+                self.new_var();
+            }
+        }
+        self.assumptions = assumptions.to_vec();
+        self.max_learnts = (self.num_clauses() as f64 / 3.0).max(100.0);
+        self.learnt_adjust_confl = 100.0;
+        self.learnt_adjust_cnt = 100;
+
+        let mut restarts = 0;
+        let result = loop {
+            let budget = match self.restarts {
+                Restarts::Luby => luby(restarts) * 100,
+                Restarts::Geometric => (100.0 * 1.5f64.powi(restarts as i32)) as usize,
+            };
+            match self.dll(budget) {
+                Some(sat) => break Some(sat),
+                None if self.interrupted() => break None,
+                None => {
+                    restarts += 1;
+                    self.stats.restarts += 1;
+                    if !self.import_shared() {
+                        break Some(false);
+                    }
+                },
+            }
+        };
+
+        if result == Some(true) {
+            self.model = self.assigns.iter().map(|val| val.unwrap_or(false)).collect();
+        }
+        self.cancel_until(0);
+        result
+    }
+
+    /// Value of `lit` in the model found by the last successful solve.
+    pub fn model_value(&self, lit: Lit) -> Option<bool> {
+        self.model.get(lit.var()).map(|&val| val != lit.is_negated())
+    }
+
+    /// The model of the last successful solve, one literal per variable.
+    pub fn model(&self) -> Vec<Lit> {
+        self.model.iter()
+            .enumerate()
+            .map(|(var, &val)| Lit::new(var, !val))
+            .collect()
+    }
+
+    /// Assumptions responsible for the last unsatisfiable answer.
+    pub fn failed_assumptions(&self) -> &[Lit] {
+        &self.failed
+    }
+
+    /// Whether `lit` was one of the assumptions used to refute the last query.
+    pub fn failed(&self, lit: Lit) -> bool {
+        self.failed.contains(&lit)
+    }
+
+    /// DPLL search with clause learning. Runs until an answer is found or
+    /// `max_conflicts` conflicts have happened, in which case it returns None
+    /// so the caller can restart. Also returns None when interrupted.
+    fn dll(&mut self, max_conflicts: usize) -> Option<bool> {
+        let mut conflicts = 0;
+        loop {
+            if self.interrupted() {
+                self.cancel_until(0);
+                return None;
+            }
+
+            let confl = match self.propagate() {
+                None => self.propagate_xors(),
+                confl => confl,
+            };
+            if !self.ok {
+                return Some(false);
+            }
+            if let Some(confl) = confl {
+                self.stats.conflicts += 1;
+                conflicts += 1;
+                if self.decision_level() == 0 {
+                    self.ok = false;
+                    return Some(false);
+                }
+
+                let (learnt, backjump) = self.analyze(confl);
+                self.cancel_until(backjump);
+                self.stats.learned += 1;
+                self.export(&learnt);
+                if learnt.len() == 1 {
+                    self.enqueue(learnt[0], None);
+                } else {
+                    let asserting = learnt[0];
+                    let cr = self.attach(learnt, true);
+                    self.bump_clause(cr);
+                    self.enqueue(asserting, Some(cr));
+                }
+                self.var_inc /= 0.95;
+                self.cla_inc /= 0.999;
+
+                // Let the learned clause database grow slowly over time.
+                self.learnt_adjust_cnt -= 1;
+                if self.learnt_adjust_cnt == 0 {
+                    self.learnt_adjust_confl *= 1.5;
+                    self.learnt_adjust_cnt = self.learnt_adjust_confl as usize;
+                    self.max_learnts *= 1.1;
+                }
+                continue;
+            }
+
+            if conflicts >= max_conflicts {
+                self.cancel_until(0);
+                return None;
+            }
+            if self.learnts.len() as f64 >= self.max_learnts + self.trail.len() as f64 {
+                self.reduce_db();
+            }
+
+            let mut next = None;
+            while self.decision_level() < self.assumptions.len() {
+                let lit = self.assumptions[self.decision_level()];
+                match lit_value(&self.assigns, lit) {
+                    Some(true) => self.trail_lim.push(self.trail.len()),
+                    Some(false) => {
+                        self.analyze_final(lit);
+                        return Some(false);
+                    },
+                    None => {
+                        next = Some(lit);
+                        break;
+                    },
+                }
+            }
+
+            let next = match next {
+                Some(lit) => lit,
+                None => match self.pick_branch_lit() {
+                    Some(lit) => {
+                        self.stats.decisions += 1;
+                        lit
+                    },
+                    None => return Some(true),
+                },
+            };
+            self.trail_lim.push(self.trail.len());
+            self.enqueue(next, None);
+        }
+    }
+
+    /// Whether the search has to stop: the interrupt flag is up or one of
+    /// the limits ran out.
+    fn interrupted(&self) -> bool {
+        if let Some(ref flag) = self.interrupt {
+            if flag.load(Ordering::Relaxed) {
+                return true;
+            }
+        }
+        self.conflict_budget.is_some_and(|n| self.stats.conflicts >= n)
+            || self.decision_budget.is_some_and(|n| self.stats.decisions >= n)
+            || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    fn export(&self, learnt: &[Lit]) {
+        if let Some((ref exchange, id)) = self.exchange {
+            if learnt.len() <= exchange.max_len {
+                exchange.publish(id, learnt);
+            }
+        }
+    }
+
+    /// Adds the clauses other solvers published since the last import.
+    /// Returns false if they make the formula unsatisfiable.
+    fn import_shared(&mut self) -> bool {
+        let incoming = match self.exchange {
+            Some((ref exchange, id)) => exchange.read(id),
+            None => Vec::new(),
+        };
+        for clause in incoming {
+            if !self.add_root_clause(&clause, true) {
+                break;
+            }
+        }
+        self.ok
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    fn enqueue(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = lit.var();
+        self.assigns[var] = Some(!lit.is_negated());
+        self.level[var] = self.decision_level();
+        self.reason[var] = reason;
+        self.trail.push(lit);
+    }
+
+    fn cancel_until(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        let lim = self.trail_lim[level];
+        if let Some(ref mut gauss) = self.xors {
+            for i in (lim..self.xhead).rev() {
+                gauss.unassign(self.trail[i].var());
+            }
+            self.xhead = cmp::min(self.xhead, lim);
+        }
+        for i in (lim..self.trail.len()).rev() {
+            let var = self.trail[i].var();
+            self.phase[var] = self.assigns[var].unwrap();
+            self.assigns[var] = None;
+            self.reason[var] = None;
+            self.order.insert(var, &self.activity);
+        }
+        self.trail.truncate(lim);
+        self.trail_lim.truncate(level);
+        self.qhead = lim;
+
+        let xor_clauses = mem::take(&mut self.xor_clauses);
+        for (var, cr) in xor_clauses {
+            if var.is_some_and(|var| self.assigns[var].is_some()) {
+                self.xor_clauses.push((var, cr));
+            } else {
+                self.release(cr);
+            }
+        }
+    }
+
+    fn pick_branch_lit(&mut self) -> Option<Lit> {
+        let mut next = None;
+        if self.random_freq > 0.0 && !self.order.heap.is_empty()
+            && self.rng.chance(self.random_freq) {
+            let var = self.order.heap[self.rng.below(self.order.heap.len())];
+            if self.assigns[var].is_none() {
+                next = Some(var);
+            }
+        }
+        while next.is_none() {
+            match self.order.pop(&self.activity) {
+                Some(var) if self.assigns[var].is_none() => next = Some(var),
+                Some(_) => {},
+                None => return None,
+            }
+        }
+
+        let var = next.unwrap();
+        let negated = match self.polarity {
+            Polarity::Saved => !self.phase[var],
+            Polarity::Negative => true,
+            Polarity::Positive => false,
+            Polarity::Random => self.rng.chance(0.5),
+        };
+        Some(Lit::new(var, negated))
+    }
+
+    /// Hands the assignments made since the last call to the XOR
+    /// constraints and feeds what they imply back into unit propagation
+    /// until neither finds anything new. Implied literals get their clause
+    /// only if conflict analysis asks for it, and no explanation outlives
+    /// the assignment it explains.
+    fn propagate_xors(&mut self) -> Option<usize> {
+        loop {
+            let deduction = match self.xors {
+                Some(ref mut gauss) => {
+                    while self.xhead < self.trail.len() {
+                        let lit = self.trail[self.xhead];
+                        gauss.assign(lit.var(), !lit.is_negated());
+                        self.xhead += 1;
+                    }
+                    gauss.deduce()
+                },
+                None => return None,
+            };
+            match deduction {
+                Deduction::Nothing => return None,
+                Deduction::Conflict(lits) => return self.xor_conflict(lits),
+                Deduction::Propagate(lits) => {
+                    // Nothing at the top level needs a reason.
+                    let reason = if self.decision_level() == 0 { None } else { Some(XOR_REASON) };
+                    for lit in lits {
+                        self.enqueue(lit, reason);
+                    }
+                },
+            }
+            if let Some(confl) = self.propagate() {
+                return Some(confl);
+            }
+        }
+    }
+
+    /// Stores the clause an XOR conflict falsifies for conflict analysis.
+    fn xor_conflict(&mut self, mut lits: Vec<Lit>) -> Option<usize> {
+        let top = lits.iter().map(|lit| self.level[lit.var()]).max().unwrap_or(0);
+        if top == 0 {
+            self.ok = false;
+            return None;
+        }
+        // Conflict analysis needs a literal from the current level.
+        self.cancel_until(top);
+        let level = &self.level;
+        lits.sort_by_key(|lit| cmp::Reverse(level[lit.var()]));
+        let cr = self.store(lits, false);
+        self.xor_clauses.push((None, cr));
+        Some(cr)
+    }
+
+    /// The clause that is the reason of `var`, built now if the XOR
+    /// constraints implied it.
+    fn reason_clause(&mut self, var: usize) -> usize {
+        match self.reason[var] {
+            Some(XOR_REASON) => {
+                let lit = Lit::new(var, self.assigns[var] == Some(false));
+                let lits = self.xors.as_ref().unwrap().reason(lit);
+                let cr = self.store(lits, false);
+                self.xor_clauses.push((Some(var), cr));
+                self.reason[var] = Some(cr);
+                cr
+            },
+            Some(cr) => cr,
+            None => panic!("variable {} has no reason", var),
+        }
+    }
+
+    /// Frees the slot of a clause nothing watches.
+    fn release(&mut self, cr: usize) {
+        self.stats.clause_bytes -= footprint(self.clauses[cr].lits.len());
+        self.clauses[cr].lits.clear();
+        self.free.push(cr);
+    }
+
+    /// Stores a clause of two or more literals and watches its first two.
+    fn attach(&mut self, lits: Vec<Lit>, learnt: bool) -> usize {
+        let watched = (lits[0], lits[1]);
+        let cr = self.store(lits, learnt);
+        self.watches[watched.0.index()].push(Watcher { cr, blocker: watched.1 });
+        self.watches[watched.1.index()].push(Watcher { cr, blocker: watched.0 });
+        if learnt {
+            self.learnts.push(cr);
+        }
+        cr
+    }
+
+    /// Stores a clause without watching it.
+    fn store(&mut self, lits: Vec<Lit>, learnt: bool) -> usize {
+        self.stats.clause_bytes += footprint(lits.len());
+        if self.stats.clause_bytes > self.stats.peak_clause_bytes {
+            self.stats.peak_clause_bytes = self.stats.clause_bytes;
+        }
+        let clause = Clause {
+            lits,
+            learnt,
+            activity: 0.0,
+        };
+        match self.free.pop() {
+            Some(cr) => {
+                self.clauses[cr] = clause;
+                cr
+            },
+            None => {
+                self.clauses.push(clause);
+                self.clauses.len() - 1
+            },
+        }
+    }
+
+    /// Unit propagation over the two watched literals of every clause.
+    /// Returns the conflicting clause if one is found.
+    fn propagate(&mut self) -> Option<usize> {
+        let mut confl = None;
+        while self.qhead < self.trail.len() && confl.is_none() {
+            let false_lit = !self.trail[self.qhead];
+            self.qhead += 1;
+            self.stats.propagations += 1;
+
+            let mut ws = mem::take(&mut self.watches[false_lit.index()]);
+            let mut i = 0;
+            let mut j = 0;
+            while i < ws.len() {
+                let watcher = ws[i];
+                i += 1;
+                if lit_value(&self.assigns, watcher.blocker) == Some(true) {
+                    ws[j] = watcher;
+                    j += 1;
+                    continue;
+                }
+
+                let cr = watcher.cr;
+                let first = {
+                    let lits = &mut self.clauses[cr].lits;
+                    if lits[0] == false_lit {
+                        lits.swap(0, 1);
+                    }
+                    lits[0]
+                };
+                let watcher = Watcher { cr, blocker: first };
+                if lit_value(&self.assigns, first) == Some(true) {
+                    ws[j] = watcher;
+                    j += 1;
+                    continue;
+                }
+
+                let mut moved = false;
+                {
+                    let lits = &mut self.clauses[cr].lits;
+                    for k in 2..lits.len() {
+                        if lit_value(&self.assigns, lits[k]) != Some(false) {
+                            lits.swap(1, k);
+                            self.watches[lits[1].index()].push(watcher);
+                            moved = true;
+                            break;
+                        }
+                    }
+                }
+                if moved {
+                    continue;
+                }
+
+                ws[j] = watcher;
+                j += 1;
+                if lit_value(&self.assigns, first) == Some(false) {
+                    confl = Some(cr);
+                    self.qhead = self.trail.len();
+                    while i < ws.len() {
+                        ws[j] = ws[i];
+                        i += 1;
+                        j += 1;
+                    }
+                } else {
+                    self.enqueue(first, Some(cr));
+                }
+            }
+            ws.truncate(j);
+            self.watches[false_lit.index()] = ws;
+        }
+        confl
+    }
+
+    /// First-UIP conflict analysis. Returns the learned clause, with the
+    /// asserting literal first, and the level to backjump to.
+    fn analyze(&mut self, confl: usize) -> (Vec<Lit>, usize) {
+        let mut learnt = vec![Lit(0)];
+        let mut path = 0;
+        let mut index = self.trail.len();
+        let mut confl = confl;
+        let mut skip_first = false;
+        let asserting;
+
+        loop {
+            if self.clauses[confl].learnt {
+                self.bump_clause(confl);
+            }
+            let start = if skip_first { 1 } else { 0 };
+            for k in start..self.clauses[confl].lits.len() {
+                let lit = self.clauses[confl].lits[k];
+                let var = lit.var();
+                if !self.seen[var] && self.level[var] > 0 {
+                    self.seen[var] = true;
+                    self.bump_var(var);
+                    if self.level[var] >= self.decision_level() {
+                        path += 1;
+                    } else {
+                        learnt.push(lit);
+                    }
+                }
+            }
+
+            loop {
+                index -= 1;
+                if self.seen[self.trail[index].var()] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            self.seen[lit.var()] = false;
+            path -= 1;
+            if path == 0 {
+                asserting = lit;
+                break;
+            }
+            confl = self.reason_clause(lit.var());
+            skip_first = true;
+        }
+        learnt[0] = !asserting;
+
+        // Drop literals implied by the rest of the clause.
+        let mut kept = vec![learnt[0]];
+        for &lit in learnt[1..].iter() {
+            let redundant = match self.reason[lit.var()] {
+                None | Some(XOR_REASON) => false,
+                Some(cr) => self.clauses[cr].lits[1..].iter().all(|q| {
+                    self.seen[q.var()] || self.level[q.var()] == 0
+                }),
+            };
+            if !redundant {
+                kept.push(lit);
+            }
+        }
+        for lit in learnt.iter() {
+            self.seen[lit.var()] = false;
+        }
+
+        let mut backjump = 0;
+        if kept.len() > 1 {
+            let mut max_i = 1;
+            for i in 2..kept.len() {
+                if self.level[kept[i].var()] > self.level[kept[max_i].var()] {
+                    max_i = i;
+                }
+            }
+            kept.swap(1, max_i);
+            backjump = self.level[kept[1].var()];
+        }
+        (kept, backjump)
+    }
+
+    /// Collects the assumptions that imply `lit`, which is an assumption
+    /// found false during search.
+    fn analyze_final(&mut self, lit: Lit) {
+        self.failed.push(lit);
+        if self.decision_level() == 0 {
+            return;
+        }
+        self.seen[lit.var()] = true;
+        for i in (self.trail_lim[0]..self.trail.len()).rev() {
+            let trail_lit = self.trail[i];
+            let var = trail_lit.var();
+            if !self.seen[var] {
+                continue;
+            }
+            match self.reason[var] {
+                None => self.failed.push(trail_lit),
+                Some(_) => {
+                    let cr = self.reason_clause(var);
+                    for k in 1..self.clauses[cr].lits.len() {
+                        let other = self.clauses[cr].lits[k].var();
+                        if self.level[other] > 0 {
+                            self.seen[other] = true;
+                        }
+                    }
+                },
+            }
+            self.seen[var] = false;
+        }
+        self.seen[lit.var()] = false;
+    }
+
+    fn bump_var(&mut self, var: usize) {
+        self.activity[var] += self.var_inc;
+        if self.activity[var] > 1e100 {
+            for act in self.activity.iter_mut() {
+                *act *= 1e-100;
+            }
+            self.var_inc *= 1e-100;
+        }
+        self.order.increased(var, &self.activity);
+    }
+
+    fn bump_clause(&mut self, cr: usize) {
+        self.clauses[cr].activity += self.cla_inc;
+        if self.clauses[cr].activity > 1e20 {
+            for &learnt in self.learnts.iter() {
+                self.clauses[learnt].activity *= 1e-20;
+            }
+            self.cla_inc *= 1e-20;
+        }
+    }
+
+    /// A clause is locked while it is the reason of a current assignment.
+    fn locked(&self, cr: usize) -> bool {
+        let first = self.clauses[cr].lits[0];
+        self.reason[first.var()] == Some(cr)
+            && lit_value(&self.assigns, first) == Some(true)
+    }
+
+    /// Forgets the less active half of the learned clauses.
+    fn reduce_db(&mut self) {
+        let mut learnts = mem::take(&mut self.learnts);
+        learnts.sort_by(|&a, &b| {
+            self.clauses[a].activity.partial_cmp(&self.clauses[b].activity).unwrap()
+        });
+        let half = learnts.len() / 2;
+        let mut kept = Vec::with_capacity(learnts.len());
+        for (i, &cr) in learnts.iter().enumerate() {
+            if i < half && self.clauses[cr].lits.len() > 2 && !self.locked(cr) {
+                // An empty clause marks a free slot until the watches are swept.
+                self.stats.clause_bytes -= footprint(self.clauses[cr].lits.len());
+                self.clauses[cr].lits.clear();
+                self.free.push(cr);
+            } else {
+                kept.push(cr);
+            }
+        }
+        self.learnts = kept;
+
+        let clauses = &self.clauses;
+        for ws in self.watches.iter_mut() {
+            ws.retain(|w| !clauses[w.cr].lits.is_empty());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing::{holds, models, random_clauses, satisfies, solver_with};
+
+    #[test]
+    fn xor_explanations_go_with_their_assignments() {
+        let mut rng = Rng::new(4);
+        let mut conflicts = 0;
+        for _ in 0..20 {
+            let mut solver = Solver::new();
+            let mut random_lits = |len: usize| -> Vec<Lit> {
+                (0..len).map(|_| Lit::new(rng.below(50), rng.chance(0.5))).collect()
+            };
+            for _ in 0..150 {
+                solver.add_clause(&random_lits(3));
+            }
+            for _ in 0..20 {
+                solver.add_xor(&random_lits(3));
+            }
+            solver.solve();
+            conflicts += solver.stats.conflicts;
+            // Back at the top level, every explanation is gone and only
+            // learnt clauses were kept.
+            assert!(solver.xor_clauses.is_empty());
+            assert!(solver.learnts.len() <= solver.stats.learned);
+        }
+        assert!(conflicts > 0);
+    }
+
+    #[test]
+    fn exchange_forgets_clauses_every_member_read() {
+        let exchange = Exchange::new(3);
+        let kept = || exchange.log.lock().unwrap().clauses.len();
+        let clause = vec![Lit::new(0, false), Lit::new(1, true)];
+        exchange.join(0);
+        exchange.join(1);
+        exchange.publish(0, &clause);
+        exchange.publish(1, &clause);
+        assert_eq!(exchange.read(0), vec![clause.clone()]);
+        assert_eq!(kept(), 2);
+        assert_eq!(exchange.read(1), vec![clause.clone()]);
+        assert_eq!(kept(), 0);
+        // A member that left holds nothing back.
+        exchange.publish(0, &clause);
+        exchange.leave(1);
+        assert_eq!(exchange.read(0), Vec::<Vec<Lit>>::new());
+        assert_eq!(kept(), 0);
+        // Joining late still reads what is kept.
+        exchange.publish(0, &clause);
+        exchange.join(2);
+        assert_eq!(exchange.read(2), vec![clause]);
+    }
+
+    fn values(solver: &Solver) -> Vec<bool> {
+        solver.model().iter().map(|lit| !lit.is_negated()).collect()
+    }
+
+    #[test]
+    fn answers_match_brute_force() {
+        let mut rng = Rng::new(1);
+        for _ in 0..500 {
+            let vars = 1 + rng.below(12);
+            let count = rng.below(6 * vars);
+            let clauses = random_clauses(&mut rng, vars, count, 3);
+            let mut solver = solver_with(vars, &clauses);
+            let sat = solver.solve();
+            assert_eq!(sat, !models(vars, &clauses).is_empty());
+            if sat {
+                assert!(satisfies(&values(&solver), &clauses));
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_clauses_keep_answers_right() {
+        let mut rng = Rng::new(2);
+        for _ in 0..100 {
+            let vars = 4 + rng.below(8);
+            let clauses = random_clauses(&mut rng, vars, 6 * vars, 3);
+            let mut solver = solver_with(vars, &[]);
+            for i in 0..clauses.len() {
+                solver.add_clause(&clauses[i]);
+                let sat = solver.solve();
+                assert_eq!(sat, !models(vars, &clauses[..i + 1]).is_empty());
+                if !sat {
+                    break;
+                }
+                assert!(satisfies(&values(&solver), &clauses[..i + 1]));
+            }
+        }
+    }
+
+    #[test]
+    fn failed_assumptions_refute_the_query() {
+        let mut rng = Rng::new(3);
+        for _ in 0..300 {
+            let vars = 2 + rng.below(10);
+            let count = rng.below(4 * vars);
+            let clauses = random_clauses(&mut rng, vars, count, 3);
+            let mut solver = solver_with(vars, &clauses);
+            // Several queries on one solver, so learned clauses carry over.
+            for _ in 0..5 {
+                let mut assumptions = Vec::new();
+                for var in 0..vars {
+                    if rng.chance(0.4) {
+                        assumptions.push(Lit::new(var, rng.chance(0.5)));
+                    }
+                }
+                // Now and then a contradictory pair, which must fail on its own.
+                if rng.chance(0.1) {
+                    assumptions.push(Lit::new(0, false));
+                    assumptions.push(Lit::new(0, true));
+                }
+                let expected = models(vars, &clauses).iter()
+                    .any(|values| assumptions.iter().all(|&lit| holds(lit, values)));
+                let sat = solver.solve_with_assumptions(&assumptions);
+                assert_eq!(sat, expected);
+                if sat {
+                    let values = values(&solver);
+                    assert!(satisfies(&values, &clauses));
+                    assert!(assumptions.iter().all(|&lit| holds(lit, &values)));
+                } else {
+                    let failed = solver.failed_assumptions().to_vec();
+                    assert!(failed.iter().all(|lit| assumptions.contains(lit) && solver.failed(*lit)));
+                    let mut refuted = clauses.clone();
+                    refuted.extend(failed.iter().map(|&lit| vec![lit]));
+                    assert!(models(vars, &refuted).is_empty());
+                }
+            }
+        }
+    }
+
+    /// Seven pigeons in six holes: variable 6 * p + h puts pigeon p in
+    /// hole h.
+    fn pigeonhole() -> Solver {
+        let mut solver = Solver::new();
+        for p in 0..7 {
+            solver.add_clause(&(0..6).map(|h| Lit::new(6 * p + h, false)).collect::<Vec<_>>());
+        }
+        for h in 0..6 {
+            for p in 0..7 {
+                for q in p + 1..7 {
+                    solver.add_clause(&[Lit::new(6 * p + h, true), Lit::new(6 * q + h, true)]);
+                }
+            }
+        }
+        solver
+    }
+
+    #[test]
+    fn refutes_the_pigeonhole_principle() {
+        let mut solver = pigeonhole();
+        assert!(!solver.solve());
+        assert!(solver.failed_assumptions().is_empty());
+        assert!(solver.stats().conflicts > 0 && solver.stats().learned > 0);
+    }
+
+    #[test]
+    fn solves_larger_random_formulas() {
+        let mut rng = Rng::new(4);
+        for _ in 0..20 {
+            let clauses = random_clauses(&mut rng, 100, 300, 3);
+            let mut solver = solver_with(100, &clauses);
+            if solver.solve() {
+                assert!(satisfies(&values(&solver), &clauses));
+            }
+        }
+    }
+
+    #[test]
+    fn limits_stop_the_search_until_raised() {
+        let mut solver = pigeonhole();
+        solver.conflict_limit(10);
+        assert_eq!(solver.solve_limited(&[]), None);
+        assert_eq!(solver.stats().conflicts, 10);
+        solver.decision_limit(5);
+        assert_eq!(solver.solve_limited(&[]), None);
+        solver.time_limit(Duration::from_secs(0));
+        assert_eq!(solver.solve_limited(&[]), None);
+
+        // The budgets count from when they are set, across calls.
+        solver.conflict_limit(usize::MAX / 2).decision_limit(usize::MAX / 2).time_limit(Duration::from_secs(600));
+        assert_eq!(solver.solve_limited(&[]), Some(false));
+        let stats = solver.stats();
+        assert_eq!(stats.solves, 4);
+        assert!(stats.propagations >= stats.decisions && stats.peak_clause_bytes >= stats.clause_bytes);
+    }
+}
@@ -0,0 +1,53 @@
+//! Small random formulas and brute-force answers for the unit tests.
+
+use rng::Rng;
+use solver::{Lit, Solver};
+
+/// Clauses of 1 to `width` distinct literals over variables `0..vars`.
+pub fn random_clauses(rng: &mut Rng, vars: usize, clauses: usize, width: usize) -> Vec<Vec<Lit>> {
+    (0..clauses)
+        .map(|_| {
+            let mut clause: Vec<Lit> = Vec::new();
+            for _ in 0..1 + rng.below(width) {
+                let lit = Lit::new(rng.below(vars), rng.chance(0.5));
+                if !clause.iter().any(|other| other.var() == lit.var()) {
+                    clause.push(lit);
+                }
+            }
+            clause
+        })
+        .collect()
+}
+
+/// Every assignment to `0..vars`, as the value of each variable.
+pub fn assignments(vars: usize) -> Vec<Vec<bool>> {
+    (0..1u64 << vars)
+        .map(|bits| (0..vars).map(|var| bits >> var & 1 == 1).collect())
+        .collect()
+}
+
+pub fn holds(lit: Lit, values: &[bool]) -> bool {
+    values[lit.var()] != lit.is_negated()
+}
+
+pub fn satisfies(values: &[bool], clauses: &[Vec<Lit>]) -> bool {
+    clauses.iter().all(|clause| clause.iter().any(|&lit| holds(lit, values)))
+}
+
+/// The assignments to `0..vars` satisfying `clauses`, sorted.
+pub fn models(vars: usize, clauses: &[Vec<Lit>]) -> Vec<Vec<bool>> {
+    let mut models: Vec<Vec<bool>> = assignments(vars).into_iter().filter(|values| satisfies(values, clauses)).collect();
+    models.sort();
+    models
+}
+
+pub fn solver_with(vars: usize, clauses: &[Vec<Lit>]) -> Solver {
+    let mut solver = Solver::new();
+    while solver.num_vars() < vars {
+        solver.new_var();
+    }
+    for clause in clauses.iter() {
+        solver.add_clause(clause);
+    }
+    solver
+}
@@ -0,0 +1,274 @@
+extern crate cue6_04;
+
+use cue6_04::rng::Rng;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Write};
+use std::process;
+
+/// A graph family with its size parameters.
+enum Family {
+    Gnp { vertices: usize, p: f64 },
+    Geometric { vertices: usize, radius: f64 },
+    Queen { rows: usize, cols: usize },
+    Mycielski { k: usize },
+    Planted { vertices: usize, k: usize, p: f64 },
+}
+
+fn usage() {
+    eprintln!("Usage: generate FAMILY ARGS [--seed N] [--hidden FILE] > FILE.col
+                      Families: gnp VERTICES P            Erdos-Renyi graph, every edge present with probability P
+                                geometric VERTICES RADIUS Random points in the unit square, joined when at most RADIUS apart
+                                queen N [M]               Queen moves on an N by M board (M = N)
+                                mycielski K               Triangle-free Mycielski graph with chromatic number K
+                                planted VERTICES K P      Edges with probability P between K hidden color classes
+                      Options:  --seed N                  Seed of the generator (0 by default)
+                                --hidden FILE             Write the hidden coloring of a planted graph to FILE")
+}
+
+/// Reports an error and exits with failure. Standard error keeps the
+/// message out of the graph, which is usually redirected to a file.
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
+
+fn parse_args() -> Option<(Family, u64, Option<String>)> {
+    let mut seed = 0;
+    let mut hidden = None;
+    let mut words = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => seed = args.next()?.parse().ok()?,
+            "--hidden" => hidden = Some(args.next()?),
+            _ => words.push(arg),
+        }
+    }
+
+    let num = |i: usize| words.get(i).and_then(|w| w.parse::<usize>().ok());
+    let real = |i: usize| words.get(i).and_then(|w| w.parse::<f64>().ok());
+    let family = match words.first().map(|w| w.as_str()) {
+        Some("gnp") => Family::Gnp { vertices: num(1)?, p: real(2)? },
+        Some("geometric") => Family::Geometric { vertices: num(1)?, radius: real(2)? },
+        Some("queen") => {
+            let rows = num(1)?;
+            let cols = if words.len() > 2 { num(2)? } else { rows };
+            Family::Queen { rows, cols }
+        },
+        Some("mycielski") => Family::Mycielski { k: num(1)? },
+        Some("planted") => Family::Planted { vertices: num(1)?, k: num(2)?, p: real(3)? },
+        _ => return None,
+    };
+    if hidden.is_some() && !matches!(family, Family::Planted { .. }) {
+        return None;
+    }
+    Some((family, seed, hidden))
+}
+
+fn gnp(rng: &mut Rng, vertices: usize, p: f64) -> Vec<(usize, usize)> {
+    let mut edges = Vec::new();
+    for u in 0..vertices {
+        for v in u + 1..vertices {
+            if rng.chance(p) {
+                edges.push((u, v));
+            }
+        }
+    }
+    edges
+}
+
+fn geometric(rng: &mut Rng, vertices: usize, radius: f64) -> Vec<(usize, usize)> {
+    let points: Vec<(f64, f64)> = (0..vertices).map(|_| (rng.next_f64(), rng.next_f64())).collect();
+    let mut edges = Vec::new();
+    for u in 0..vertices {
+        for v in u + 1..vertices {
+            let (dx, dy) = (points[u].0 - points[v].0, points[u].1 - points[v].1);
+            if dx * dx + dy * dy <= radius * radius {
+                edges.push((u, v));
+            }
+        }
+    }
+    edges
+}
+
+/// Square `row * cols + col`; two squares are adjacent when a queen could
+/// move between them.
+fn queen(rows: usize, cols: usize) -> Vec<(usize, usize)> {
+    let mut edges = Vec::new();
+    for u in 0..rows * cols {
+        for v in u + 1..rows * cols {
+            let (r1, c1, r2, c2) = (u / cols, u % cols, v / cols, v % cols);
+            let diagonal = r2 - r1 == c1.max(c2) - c1.min(c2);
+            if r1 == r2 || c1 == c2 || diagonal {
+                edges.push((u, v));
+            }
+        }
+    }
+    edges
+}
+
+/// The Mycielski graph M_k: a single vertex for k = 1, otherwise an edge
+/// with the Mycielski construction applied k - 2 times. Every vertex gets a
+/// shadow adjacent to its neighbors, and one more vertex is adjacent to all
+/// shadows; each step raises the chromatic number by one without creating
+/// a triangle. M_3 is C5 and M_4 the Grötzsch graph, the DIMACS myciel3.
+fn mycielski(k: usize) -> (usize, Vec<(usize, usize)>) {
+    if k == 1 {
+        return (1, Vec::new());
+    }
+    let mut vertices = 2;
+    let mut edges = vec![(0, 1)];
+    for _ in 2..k {
+        let mut next = edges.clone();
+        for &(u, v) in edges.iter() {
+            next.push((u, vertices + v));
+            next.push((v, vertices + u));
+        }
+        for shadow in vertices..2 * vertices {
+            next.push((shadow, 2 * vertices));
+        }
+        vertices = 2 * vertices + 1;
+        edges = next;
+    }
+    (vertices, edges)
+}
+
+/// Gives every vertex a hidden color in `1..k + 1` and only joins vertices
+/// of different colors, so the hidden coloring is always proper.
+fn planted(rng: &mut Rng, vertices: usize, k: usize, p: f64) -> (Vec<usize>, Vec<(usize, usize)>) {
+    let colors: Vec<usize> = (0..vertices).map(|_| rng.below(k) + 1).collect();
+    let mut edges = Vec::new();
+    for u in 0..vertices {
+        for v in u + 1..vertices {
+            if colors[u] != colors[v] && rng.chance(p) {
+                edges.push((u, v));
+            }
+        }
+    }
+    (colors, edges)
+}
+
+fn write_hidden(path: &str, k: usize, colors: &[usize]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    writeln!(file, "s col {}", k)?;
+    for (v, color) in (1..).zip(colors.iter()) {
+        writeln!(file, "l {} {}", v, color)?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let (family, seed, hidden) = match parse_args() {
+        Some(parsed) => parsed,
+        None => { usage(); process::exit(1) },
+    };
+    let mut rng = Rng::new(seed);
+
+    let (vertices, edges, name) = match family {
+        Family::Gnp { vertices, p } => {
+            if !(0.0..=1.0).contains(&p) {
+                fail("P must be between 0 and 1");
+            }
+            (vertices, gnp(&mut rng, vertices, p), format!("gnp vertices={} p={}", vertices, p))
+        },
+        Family::Geometric { vertices, radius } => {
+            if radius < 0.0 {
+                fail("RADIUS must not be negative");
+            }
+            (vertices, geometric(&mut rng, vertices, radius),
+             format!("geometric vertices={} radius={}", vertices, radius))
+        },
+        Family::Queen { rows, cols } => {
+            (rows * cols, queen(rows, cols), format!("queen rows={} cols={}", rows, cols))
+        },
+        Family::Mycielski { k } => {
+            if k == 0 {
+                fail("K must be positive");
+            }
+            let (vertices, edges) = mycielski(k);
+            (vertices, edges, format!("mycielski k={}", k))
+        },
+        Family::Planted { vertices, k, p } => {
+            if k == 0 || !(0.0..=1.0).contains(&p) {
+                fail("K must be positive and P between 0 and 1");
+            }
+            let (colors, edges) = planted(&mut rng, vertices, k, p);
+            if let Some(ref path) = hidden {
+                if let Err(e) = write_hidden(path, k, &colors) {
+                    fail(&format!("Could not write {}: {}", path, e));
+                }
+            }
+            (vertices, edges, format!("planted vertices={} k={} p={}", vertices, k, p))
+        },
+    };
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let written = writeln!(out, "c {} seed={}", name, seed)
+        .and_then(|_| writeln!(out, "p edge {} {}", vertices, edges.len()))
+        .and_then(|_| {
+            for &(u, v) in edges.iter() {
+                writeln!(out, "e {} {}", u + 1, v + 1)?;
+            }
+            Ok(())
+        });
+    // A reader that stops early, like head, is no error.
+    match written {
+        Err(ref e) if e.kind() != io::ErrorKind::BrokenPipe => fail(&format!("Could not write the graph: {}", e)),
+        _ => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle_free(vertices: usize, edges: &[(usize, usize)]) -> bool {
+        let mut adjacent = vec![vec![false; vertices]; vertices];
+        for &(u, v) in edges.iter() {
+            adjacent[u][v] = true;
+            adjacent[v][u] = true;
+        }
+        edges.iter().all(|&(u, v)| (0..vertices).all(|w| !(adjacent[u][w] && adjacent[v][w])))
+    }
+
+    #[test]
+    fn mycielski_matches_the_standard_graphs() {
+        let sizes = [(1, 0), (2, 1), (5, 5), (11, 20), (23, 71), (47, 236)];
+        for (k, &(vertices, edges)) in (1..).zip(sizes.iter()) {
+            let (n, graph) = mycielski(k);
+            assert_eq!((n, graph.len()), (vertices, edges));
+            assert!(triangle_free(n, &graph));
+        }
+    }
+
+    #[test]
+    fn queen_graphs_join_attacking_squares() {
+        assert_eq!(queen(5, 5).len(), 160);
+        assert_eq!(queen(8, 8).len(), 728);
+        assert_eq!(queen(1, 4), vec![(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)]);
+        // Corner to corner of a 2 by 3 board is no queen move.
+        assert!(!queen(2, 3).contains(&(0, 5)));
+    }
+
+    #[test]
+    fn planted_coloring_is_proper() {
+        let mut rng = Rng::new(3);
+        let (colors, edges) = planted(&mut rng, 60, 4, 0.5);
+        assert!(colors.iter().all(|color| (1..=4).contains(color)));
+        assert!(edges.iter().all(|&(u, v)| colors[u] != colors[v]));
+        assert!(!edges.is_empty());
+    }
+
+    #[test]
+    fn seeds_reproduce_graphs() {
+        let graph = |seed| gnp(&mut Rng::new(seed), 30, 0.3);
+        assert_eq!(graph(5), graph(5));
+        assert_ne!(graph(5), graph(6));
+        let points = |seed| geometric(&mut Rng::new(seed), 30, 0.3);
+        assert_eq!(points(5), points(5));
+        assert!(gnp(&mut Rng::new(1), 20, 1.0).len() == 190 && gnp(&mut Rng::new(1), 20, 0.0).is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+extern crate cue6_04;
+
+use cue6_04::{Algorithm, Graph};
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+
+fn usage() {
+    println!("Usage: sudoku [--latin] [PUZZLE]
+              Solves an n^2 by n^2 Sudoku, read from PUZZLE or stdin, as a graph
+              coloring with the clues precolored, and prints the filled grid.
+              Each line is a row. Cells are the characters 1-9 and A-Z (10-35), or
+              numbers separated by spaces; . 0 and _ are empty, and | + - are ignored.
+              Options: --latin  Complete a Latin square: rows and columns only, any size");
+}
+
+/// The value of a one-character cell, Some(None) if it is empty.
+fn symbol(c: char) -> Option<Option<usize>> {
+    match c {
+        '.' | '0' | '_' => Some(None),
+        _ => c.to_digit(36).map(|value| Some(value as usize)),
+    }
+}
+
+fn show(value: usize, size: usize) -> String {
+    if size < 36 {
+        std::char::from_digit(value as u32, 36).unwrap().to_ascii_uppercase().to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Reads the rows of the grid, with None for an empty cell.
+fn read_grid(text: &str) -> Result<Vec<Vec<Option<usize>>>, String> {
+    let mut grid = Vec::new();
+    for (number, line) in (1..).zip(text.lines()) {
+        let line: String = line.chars().map(|c| if "|+-".contains(c) { ' ' } else { c }).collect();
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let cells: Option<Vec<Option<usize>>> = if words.len() == 1 {
+            words[0].chars().map(symbol).collect()
+        } else {
+            words.iter()
+                .map(|word| match word.parse() {
+                    Ok(0) => Some(None),
+                    Ok(value) => Some(Some(value)),
+                    Err(_) if word.chars().count() == 1 => symbol(word.chars().next().unwrap()),
+                    Err(_) => None,
+                })
+                .collect()
+        };
+        match cells {
+            Some(ref cells) if cells.is_empty() => continue,
+            Some(cells) => grid.push(cells),
+            None => return Err(format!("line {}: bad cell in {}", number, line.trim())),
+        }
+    }
+    Ok(grid)
+}
+
+/// Side of the boxes of a Sudoku of the given size.
+fn box_side(size: usize) -> Option<usize> {
+    (1..size + 1).find(|&side| side * side == size)
+}
+
+/// The coloring problem for `grid`, with boxes of the given side for a
+/// Sudoku and without for a Latin square.
+fn puzzle(grid: &[Vec<Option<usize>>], side: Option<usize>) -> Graph {
+    let size = grid.len();
+    // Cell (row, col) is vertex row * size + col + 1; cells sharing a row,
+    // a column or a box must differ.
+    let peers = |u: usize, v: usize| {
+        let (r1, c1, r2, c2) = (u / size, u % size, v / size, v % size);
+        r1 == r2 || c1 == c2 || side.is_some_and(|side| r1 / side == r2 / side && c1 / side == c2 / side)
+    };
+    let cells = size * size;
+    let mut edges = Vec::new();
+    for u in 0..cells {
+        for v in u + 1..cells {
+            if peers(u, v) {
+                edges.push((u, v));
+            }
+        }
+    }
+
+    let mut graph = Graph::new();
+    graph.verts(cells).edges(edges.len()).colors(size);
+    for &(u, v) in edges.iter() {
+        graph.connect(u + 1, v + 1);
+    }
+    for (i, cell) in grid.iter().flat_map(|row| row.iter()).enumerate() {
+        if let Some(value) = *cell {
+            graph.fix(i + 1, value);
+        }
+    }
+    graph
+}
+
+fn main() {
+    let mut latin = false;
+    let mut path = None;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--latin" => latin = true,
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => { usage(); return },
+        }
+    }
+
+    let mut text = String::new();
+    let read = match path {
+        Some(ref path) => File::open(path).and_then(|mut file| file.read_to_string(&mut text)),
+        None => io::stdin().read_to_string(&mut text),
+    };
+    if let Err(e) = read {
+        println!("Could not read the puzzle: {}", e);
+        return;
+    }
+    let grid = match read_grid(&text) {
+        Ok(grid) => grid,
+        Err(e) => { println!("Could not read the puzzle: {}", e); return },
+    };
+
+    let size = grid.len();
+    if size == 0 || grid.iter().any(|row| row.len() != size) {
+        println!("The grid must be square");
+        return;
+    }
+    let side = if latin { None } else {
+        match box_side(size) {
+            Some(side) => Some(side),
+            None => { println!("A Sudoku needs n^2 rows, not {}", size); return },
+        }
+    };
+    if let Some(value) = grid.iter().flat_map(|row| row.iter()).filter_map(|&cell| cell).find(|&value| value > size) {
+        println!("Clue {} does not fit a grid of size {}", value, size);
+        return;
+    }
+
+    let mut graph = puzzle(&grid, side);
+    match graph.find_coloring(Algorithm::ArcConsistency) {
+        Some(colors) => {
+            for (r, row) in colors.chunks(size).enumerate() {
+                if side.is_some_and(|side| r > 0 && r % side == 0) {
+                    println!();
+                }
+                let mut line = Vec::new();
+                for (c, &value) in row.iter().enumerate() {
+                    if side.is_some_and(|side| c > 0 && c % side == 0) {
+                        line.push("|".to_string());
+                    }
+                    line.push(format!("{:>1$}", show(value, size), show(size, size).len()));
+                }
+                println!("{}", line.join(" "));
+            }
+        },
+        None => println!("No Solution."),
+    }
+    println!("{} branching nodes explored.", graph.explored);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// True if `colors` fills `grid` keeping its clues, with every value
+    /// once in each row, column and box.
+    fn solves(grid: &[Vec<Option<usize>>], side: Option<usize>, colors: &[usize]) -> bool {
+        let size = grid.len();
+        let clues = grid.iter().flat_map(|row| row.iter()).zip(colors.iter())
+            .all(|(&cell, &value)| cell.is_none_or(|clue| clue == value));
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for i in 0..size {
+            groups.push((0..size).map(|j| colors[i * size + j]).collect());
+            groups.push((0..size).map(|j| colors[j * size + i]).collect());
+            if let Some(side) = side {
+                let (top, left) = (i / side * side, i % side * side);
+                groups.push((0..size).map(|j| colors[(top + j / side) * size + left + j % side]).collect());
+            }
+        }
+        clues && groups.iter_mut().all(|group| {
+            group.sort();
+            *group == (1..size + 1).collect::<Vec<usize>>()
+        })
+    }
+
+    #[test]
+    fn solves_a_known_sudoku() {
+        let grid = read_grid("53..7....\n6..195...\n.98....6.\n8...6...3\n4..8.3..1\n\
+                              7...2...6\n.6....28.\n...419..5\n....8..79\n").unwrap();
+        let colors = puzzle(&grid, box_side(9)).find_coloring(Algorithm::ArcConsistency).unwrap();
+        assert!(solves(&grid, Some(3), &colors));
+        assert_eq!(colors[..9], [5, 3, 4, 6, 7, 8, 9, 1, 2]);
+    }
+
+    #[test]
+    fn completes_latin_squares_and_rejects_clashes() {
+        let grid = vec![vec![None; 7]; 7];
+        let colors = puzzle(&grid, None).find_coloring(Algorithm::ArcConsistency).unwrap();
+        assert!(solves(&grid, None, &colors));
+        // Two 1s in the top row of a 4 by 4 Sudoku.
+        let grid = read_grid("1 . 1 .\n. . . .\n. . . .\n. . . .\n").unwrap();
+        assert_eq!(puzzle(&grid, Some(2)).find_coloring(Algorithm::ArcConsistency), None);
+    }
+
+    #[test]
+    fn reads_every_cell_format() {
+        let grid = vec![vec![Some(1), None, Some(12), None]];
+        assert_eq!(read_grid("1.C_\n").unwrap(), grid);
+        assert_eq!(read_grid("1 0 12 .\n\n").unwrap(), grid);
+        assert_eq!(read_grid("1 . | C _\n+---+\n").unwrap(), grid);
+        assert!(read_grid("1 2 x3\n").is_err());
+        assert_eq!((box_side(16), box_side(12)), (Some(4), None));
+    }
+}
@@ -0,0 +1,107 @@
+extern crate cue6_04;
+
+use cue6_04::Graph;
+
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+fn usage() {
+    println!("Usage: verify GRAPH.col COLORING [--dot FILE]
+              Checks a coloring in the s col / l V C format against a DIMACS
+              graph and reports conflicting edges, uncolored vertices and
+              colors outside the graph's f and a directives.
+              Options: --dot FILE  Also draw the colored graph in Graphviz DOT");
+}
+
+/// Reads a DIMACS `.col` graph on its own, so the check does not depend on
+/// the solver's reader. Lines other than `p`, `e`, `f` and `a` are skipped.
+fn read_graph(path: &str) -> Result<Graph, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut graph = Graph::new();
+    let mut vertices = None;
+    for (number, line) in (1..).zip(BufReader::new(file).lines()) {
+        let line = line.map_err(|e| e.to_string())?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let numbers: Vec<Option<usize>> = words.iter().skip(1).map(|word| word.parse().ok()).collect();
+        match words.first() {
+            Some(&"p") => match (words.get(1), numbers.get(1), numbers.get(2)) {
+                (Some(_), Some(&Some(verts)), Some(&Some(edges))) if vertices.is_none() => {
+                    graph.verts(verts).edges(edges);
+                    vertices = Some(verts);
+                },
+                _ => return Err(format!("line {}: bad problem line {}", number, line)),
+            },
+            Some(&"e") => match (vertices, numbers.first(), numbers.get(1)) {
+                (Some(n), Some(&Some(u)), Some(&Some(v))) if 1 <= u && u <= n && 1 <= v && v <= n => {
+                    graph.connect(u, v);
+                },
+                (None, _, _) => return Err(format!("line {}: edge before the problem line", number)),
+                _ => return Err(format!("line {}: bad edge {}", number, line)),
+            },
+            Some(&"f") | Some(&"a") => {
+                let colors: Option<Vec<usize>> = numbers.iter().skip(1).cloned().collect();
+                match (vertices, numbers.first(), colors) {
+                    (Some(n), Some(&Some(v)), Some(ref colors))
+                        if 1 <= v && v <= n && (words[0] == "a" || colors.len() == 1) => graph.allow(v, colors),
+                    (None, _, _) => return Err(format!("line {}: color limit before the problem line", number)),
+                    _ => return Err(format!("line {}: bad color limit {}", number, line)),
+                }
+            },
+            _ => continue,
+        }
+    }
+    match vertices {
+        Some(_) => Ok(graph),
+        None => Err("no problem line".to_string()),
+    }
+}
+
+fn main() {
+    let mut paths = Vec::new();
+    let mut dot = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dot" => match args.next() {
+                Some(path) => dot = Some(path),
+                None => { usage(); return },
+            },
+            _ => paths.push(arg),
+        }
+    }
+    if paths.len() != 2 {
+        usage();
+        return;
+    }
+
+    let mut graph = match read_graph(&paths[0]) {
+        Ok(graph) => graph,
+        Err(e) => { println!("Could not read {}: {}", paths[0], e); return },
+    };
+    let colored = File::open(&paths[1])
+        .map_err(|e| e.to_string())
+        .and_then(|file| graph.read_coloring(BufReader::new(file)));
+    match colored {
+        Ok(true) => {},
+        Ok(false) => { println!("{} contains no coloring.", paths[1]); return },
+        Err(e) => { println!("Could not read {}: {}", paths[1], e); return },
+    }
+
+    let faults = graph.faults();
+    for fault in faults.iter() {
+        println!("{}", fault);
+    }
+    if faults.is_empty() {
+        println!("Coloring is valid.");
+    } else {
+        println!("Coloring is invalid.");
+    }
+
+    if let Some(path) = dot {
+        let written = File::create(&path).and_then(|mut file| graph.write_dot(&mut file));
+        if let Err(e) = written {
+            println!("Could not write {}: {}", path, e);
+        }
+    }
+}
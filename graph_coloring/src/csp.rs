@@ -0,0 +1,772 @@
+use std::collections::VecDeque;
+
+/// The values each variable may still take.
+pub struct Domains {
+    values: Vec<Vec<usize>>,
+    alive: Vec<Vec<bool>>,
+    size: Vec<usize>,
+}
+
+impl Domains {
+    pub fn contains(&self, var: usize, value: usize) -> bool {
+        match self.values[var].binary_search(&value) {
+            Ok(i) => self.alive[var][i],
+            Err(_) => false,
+        }
+    }
+
+    pub fn size(&self, var: usize) -> usize {
+        self.size[var]
+    }
+
+    /// The remaining values of `var`, in increasing order.
+    pub fn values(&self, var: usize) -> Vec<usize> {
+        self.values[var].iter()
+            .zip(self.alive[var].iter())
+            .filter(|&(_, &alive)| alive)
+            .map(|(&value, _)| value)
+            .collect()
+    }
+
+    /// The value of `var` once only one is left.
+    pub fn single(&self, var: usize) -> Option<usize> {
+        if self.size[var] != 1 {
+            return None;
+        }
+        self.values[var].iter()
+            .zip(self.alive[var].iter())
+            .find(|&(_, &alive)| alive)
+            .map(|(&value, _)| value)
+    }
+
+    fn set(&mut self, var: usize, value: usize, alive: bool) -> bool {
+        match self.values[var].binary_search(&value) {
+            Ok(i) if self.alive[var][i] != alive => {
+                self.alive[var][i] = alive;
+                if alive {
+                    self.size[var] += 1;
+                } else {
+                    self.size[var] -= 1;
+                }
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+/// A constraint on the values of the variables in its scope.
+pub trait Constraint {
+    fn scope(&self) -> &[usize];
+
+    /// Whether values for the whole scope, given in scope order, satisfy it.
+    fn check(&self, values: &[usize]) -> bool;
+
+    /// Values that can no longer be part of a solution, as (variable, value)
+    /// pairs, or None if the constraint cannot be satisfied any more. The
+    /// default is forward checking: once all but one variable of the scope
+    /// is fixed, the last one keeps only the values `check` accepts.
+    fn prune(&self, domains: &Domains) -> Option<Vec<(usize, usize)>> {
+        let scope = self.scope();
+        let open: Vec<usize> = (0..scope.len()).filter(|&i| domains.size(scope[i]) != 1).collect();
+        if open.len() > 1 {
+            return Some(Vec::new());
+        }
+        let mut values: Vec<usize> = scope.iter()
+            .map(|&var| domains.single(var).unwrap_or(0))
+            .collect();
+        match open.first() {
+            None => if self.check(&values) { Some(Vec::new()) } else { None },
+            Some(&i) => {
+                let mut removed = Vec::new();
+                for value in domains.values(scope[i]) {
+                    values[i] = value;
+                    if !self.check(&values) {
+                        removed.push((scope[i], value));
+                    }
+                }
+                Some(removed)
+            },
+        }
+    }
+}
+
+/// Two variables take different values.
+pub struct NotEqual {
+    scope: [usize; 2],
+}
+
+impl NotEqual {
+    pub fn new(a: usize, b: usize) -> NotEqual {
+        NotEqual { scope: [a, b] }
+    }
+}
+
+impl Constraint for NotEqual {
+    fn scope(&self) -> &[usize] {
+        &self.scope
+    }
+
+    fn check(&self, values: &[usize]) -> bool {
+        values[0] != values[1]
+    }
+}
+
+/// Every variable in the scope takes a different value.
+pub struct AllDifferent {
+    scope: Vec<usize>,
+}
+
+impl AllDifferent {
+    pub fn new(scope: &[usize]) -> AllDifferent {
+        AllDifferent { scope: scope.to_vec() }
+    }
+}
+
+impl Constraint for AllDifferent {
+    fn scope(&self) -> &[usize] {
+        &self.scope
+    }
+
+    fn check(&self, values: &[usize]) -> bool {
+        let mut sorted = values.to_vec();
+        sorted.sort();
+        sorted.windows(2).all(|pair| pair[0] != pair[1])
+    }
+
+    /// Removes the value of every fixed variable from the others, and fails
+    /// when fewer values remain than there are variables.
+    fn prune(&self, domains: &Domains) -> Option<Vec<(usize, usize)>> {
+        let mut union: Vec<usize> = self.scope.iter().flat_map(|&var| domains.values(var)).collect();
+        union.sort();
+        union.dedup();
+        if union.len() < self.scope.len() {
+            return None;
+        }
+
+        let mut removed = Vec::new();
+        for &var in self.scope.iter() {
+            let value = match domains.single(var) {
+                Some(value) => value,
+                None => continue,
+            };
+            for &other in self.scope.iter() {
+                if other == var {
+                    continue;
+                }
+                if domains.single(other) == Some(value) {
+                    return None;
+                }
+                if domains.contains(other, value) {
+                    removed.push((other, value));
+                }
+            }
+        }
+        Some(removed)
+    }
+}
+
+/// The scope takes one of the listed tuples of values.
+pub struct Table {
+    scope: Vec<usize>,
+    tuples: Vec<Vec<usize>>,
+}
+
+impl Table {
+    pub fn new(scope: &[usize], tuples: Vec<Vec<usize>>) -> Table {
+        Table {
+            scope: scope.to_vec(),
+            tuples,
+        }
+    }
+}
+
+impl Constraint for Table {
+    fn scope(&self) -> &[usize] {
+        &self.scope
+    }
+
+    fn check(&self, values: &[usize]) -> bool {
+        self.tuples.iter().any(|tuple| tuple[..] == values[..])
+    }
+
+    /// Keeps only values that appear in some tuple still allowed by every
+    /// domain (generalized arc consistency).
+    fn prune(&self, domains: &Domains) -> Option<Vec<(usize, usize)>> {
+        let live: Vec<&Vec<usize>> = self.tuples.iter()
+            .filter(|tuple| self.scope.iter().zip(tuple.iter()).all(|(&var, &value)| domains.contains(var, value)))
+            .collect();
+        if live.is_empty() {
+            return None;
+        }
+        let mut removed = Vec::new();
+        for (i, &var) in self.scope.iter().enumerate() {
+            for value in domains.values(var) {
+                if !live.iter().any(|tuple| tuple[i] == value) {
+                    removed.push((var, value));
+                }
+            }
+        }
+        Some(removed)
+    }
+}
+
+/// Any test on the values of the scope, checked by forward checking.
+pub struct Predicate<F> {
+    scope: Vec<usize>,
+    test: F,
+}
+
+impl<F: Fn(&[usize]) -> bool> Predicate<F> {
+    pub fn new(scope: &[usize], test: F) -> Predicate<F> {
+        Predicate {
+            scope: scope.to_vec(),
+            test,
+        }
+    }
+}
+
+impl<F: Fn(&[usize]) -> bool> Constraint for Predicate<F> {
+    fn scope(&self) -> &[usize] {
+        &self.scope
+    }
+
+    fn check(&self, values: &[usize]) -> bool {
+        (self.test)(values)
+    }
+}
+
+/// How the search picks the next variable and what it checks once the
+/// variable has a value.
+#[derive(Clone, Copy)]
+pub enum Strategy {
+    /// Variables in order, checking each constraint once its whole scope
+    /// has values.
+    Backtrack,
+    /// Variables in order, pruning the others through the constraints on
+    /// the variable just assigned.
+    ForwardCheck,
+    /// Forward checking, branching on the variable with the fewest values
+    /// left.
+    MostConstrained,
+    /// Every constraint propagated after each decision, branching on the
+    /// variable with the fewest values left.
+    Maintain,
+}
+
+/// A constraint satisfaction problem over variables with finite domains of
+/// non-negative integers. Graph coloring builds one and runs its dfs, fc,
+/// mcv and csp algorithms on it.
+pub struct Csp {
+    domains: Domains,
+    constraints: Vec<Box<dyn Constraint>>,
+    /// Constraints mentioning each variable.
+    watching: Vec<Vec<usize>>,
+    strategy: Strategy,
+    /// The values 1..used already taken, if values are interchangeable.
+    interchangeable: Option<usize>,
+    pub explored: usize,
+}
+
+impl Default for Csp {
+    fn default() -> Csp {
+        Csp::new()
+    }
+}
+
+impl Csp {
+    pub fn new() -> Csp {
+        Csp {
+            domains: Domains {
+                values: Vec::new(),
+                alive: Vec::new(),
+                size: Vec::new(),
+            },
+            constraints: Vec::new(),
+            watching: Vec::new(),
+            strategy: Strategy::Maintain,
+            interchangeable: None,
+            explored: 0,
+        }
+    }
+
+    /// Adds a variable that may take any of `domain` and returns its index.
+    pub fn variable(&mut self, domain: &[usize]) -> usize {
+        let mut values = domain.to_vec();
+        values.sort();
+        values.dedup();
+        self.domains.size.push(values.len());
+        self.domains.alive.push(vec![true; values.len()]);
+        self.domains.values.push(values);
+        self.watching.push(Vec::new());
+        self.domains.values.len() - 1
+    }
+
+    /// Limits `var` to `value` alone.
+    pub fn fix(&mut self, var: usize, value: usize) -> &mut Self {
+        for i in 0..self.domains.values[var].len() {
+            let other = self.domains.values[var][i];
+            self.domains.set(var, other, other == value);
+        }
+        self
+    }
+
+    pub fn strategy(&mut self, strategy: Strategy) -> &mut Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Declares the values 1, 2, ... interchangeable, as colors are, with
+    /// 1..used taken already: a variable then tries at most one value above
+    /// the largest taken so far. Only sound if renaming values maps every
+    /// solution to another one.
+    pub fn interchangeable(&mut self, used: usize) -> &mut Self {
+        self.interchangeable = Some(used);
+        self
+    }
+
+    pub fn constrain<C: Constraint + 'static>(&mut self, constraint: C) -> &mut Self {
+        let id = self.constraints.len();
+        for &var in constraint.scope() {
+            if !self.watching[var].contains(&id) {
+                self.watching[var].push(id);
+            }
+        }
+        self.constraints.push(Box::new(constraint));
+        self
+    }
+
+    /// Finds a value for every variable satisfying all constraints.
+    pub fn solve(&mut self) -> Option<Vec<usize>> {
+        if self.domains.size.contains(&0) {
+            return None;
+        }
+        let mut trail = Vec::new();
+        let mut assigned = vec![false; self.watching.len()];
+        let started = match self.strategy {
+            Strategy::Maintain => {
+                let all: Vec<usize> = (0..self.constraints.len()).collect();
+                self.propagate(all, &mut trail)
+            },
+            _ => self.assign_singles(&mut assigned, &mut trail),
+        };
+        let used = self.interchangeable.unwrap_or(0);
+        if !started || !self.search(&mut assigned, used, &mut trail) {
+            self.undo(&mut trail, 0);
+            return None;
+        }
+        let solution = (0..self.watching.len())
+            .map(|var| self.domains.single(var).unwrap())
+            .collect();
+        self.undo(&mut trail, 0);
+        Some(solution)
+    }
+
+    fn remove(&mut self, var: usize, value: usize, trail: &mut Vec<(usize, usize)>) -> bool {
+        if self.domains.set(var, value, false) {
+            trail.push((var, value));
+            true
+        } else {
+            false
+        }
+    }
+
+    fn undo(&mut self, trail: &mut Vec<(usize, usize)>, mark: usize) {
+        while trail.len() > mark {
+            let (var, value) = trail.pop().unwrap();
+            self.domains.set(var, value, true);
+        }
+    }
+
+    /// Runs the constraints in `queue`, and those on any variable they
+    /// prune, until nothing changes. Returns false on a wipeout.
+    fn propagate(&mut self, queue: Vec<usize>, trail: &mut Vec<(usize, usize)>) -> bool {
+        let mut queued = vec![false; self.constraints.len()];
+        let mut queue: VecDeque<usize> = queue.into_iter().collect();
+        for &id in queue.iter() {
+            queued[id] = true;
+        }
+        while let Some(id) = queue.pop_front() {
+            queued[id] = false;
+            let removed = match self.constraints[id].prune(&self.domains) {
+                Some(removed) => removed,
+                None => return false,
+            };
+            for (var, value) in removed {
+                if !self.remove(var, value, trail) {
+                    continue;
+                }
+                if self.domains.size(var) == 0 {
+                    return false;
+                }
+                for &other in self.watching[var].iter() {
+                    if !queued[other] {
+                        queued[other] = true;
+                        queue.push_back(other);
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Takes the variables with a single value as assigned before the
+    /// search and prunes the others through their constraints.
+    fn assign_singles(&mut self, assigned: &mut [bool], trail: &mut Vec<(usize, usize)>) -> bool {
+        for (var, assigned) in assigned.iter_mut().enumerate() {
+            if self.domains.size(var) == 1 {
+                *assigned = true;
+                if !self.forward_check(var, trail) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Prunes the values the constraints on `var` rule out. Returns false
+    /// if one of them fails or a domain runs empty.
+    fn forward_check(&mut self, var: usize, trail: &mut Vec<(usize, usize)>) -> bool {
+        for id in self.watching[var].clone() {
+            let removed = match self.constraints[id].prune(&self.domains) {
+                Some(removed) => removed,
+                None => return false,
+            };
+            for (other, value) in removed {
+                if self.remove(other, value, trail) && self.domains.size(other) == 0 {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// The variable to branch on next, or None once every one has a value.
+    /// With every constraint propagated, a variable with a single value
+    /// left needs no decision.
+    fn next(&self, assigned: &[bool]) -> Option<usize> {
+        let mut open = (0..self.watching.len()).filter(|&var| !assigned[var]);
+        match self.strategy {
+            Strategy::Backtrack | Strategy::ForwardCheck => open.next(),
+            Strategy::MostConstrained => open.min_by_key(|&var| self.domains.size(var)),
+            Strategy::Maintain => open.filter(|&var| self.domains.size(var) > 1)
+                .min_by_key(|&var| self.domains.size(var)),
+        }
+    }
+
+    /// Whether the value just given to `var` can still be part of a
+    /// solution, pruning what the strategy prunes.
+    fn consistent(&mut self, var: usize, assigned: &[bool], trail: &mut Vec<(usize, usize)>) -> bool {
+        match self.strategy {
+            Strategy::Backtrack => {
+                let mut values = Vec::new();
+                self.watching[var].iter().all(|&id| {
+                    let scope = self.constraints[id].scope();
+                    if !scope.iter().all(|&other| assigned[other]) {
+                        return true;
+                    }
+                    values.clear();
+                    values.extend(scope.iter().map(|&other| self.domains.single(other).unwrap()));
+                    self.constraints[id].check(&values)
+                })
+            },
+            Strategy::ForwardCheck | Strategy::MostConstrained => self.forward_check(var, trail),
+            Strategy::Maintain => {
+                let watching = self.watching[var].clone();
+                self.propagate(watching, trail)
+            },
+        }
+    }
+
+    /// Values above `used` + 1 are skipped when values are interchangeable.
+    fn search(&mut self, assigned: &mut [bool], used: usize, trail: &mut Vec<(usize, usize)>) -> bool {
+        let var = match self.next(assigned) {
+            Some(var) => var,
+            None => return true,
+        };
+
+        assigned[var] = true;
+        let values = self.domains.values(var);
+        for &value in values.iter() {
+            if self.interchangeable.is_some() && value > used + 1 {
+                break;
+            }
+            self.explored += 1;
+            let mark = trail.len();
+            for &other in values.iter() {
+                if other != value {
+                    self.remove(var, other, trail);
+                }
+            }
+            if self.consistent(var, assigned, trail) && self.search(assigned, used.max(value), trail) {
+                return true;
+            }
+            self.undo(trail, mark);
+        }
+        assigned[var] = false;
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rng::Rng;
+
+    /// Every assignment of values from `domains`.
+    fn assignments(domains: &[Vec<usize>]) -> Vec<Vec<usize>> {
+        let mut all = vec![Vec::new()];
+        for domain in domains.iter() {
+            all = all.iter()
+                .flat_map(|prefix| domain.iter().map(move |&value| {
+                    let mut next: Vec<usize> = prefix.clone();
+                    next.push(value);
+                    next
+                }))
+                .collect();
+        }
+        all
+    }
+
+    /// Domains drawn from 0..4, sometimes empty.
+    fn random_domains(rng: &mut Rng, vars: usize) -> Vec<Vec<usize>> {
+        (0..vars).map(|_| (0..4).filter(|_| rng.chance(0.7)).collect()).collect()
+    }
+
+    fn random_scope(rng: &mut Rng, vars: usize, most: usize) -> Vec<usize> {
+        let mut scope: Vec<usize> = (0..vars).collect();
+        for i in (1..scope.len()).rev() {
+            scope.swap(i, rng.below(i + 1));
+        }
+        scope.truncate(1 + rng.below(most.min(vars)));
+        scope
+    }
+
+    fn project(values: &[usize], scope: &[usize]) -> Vec<usize> {
+        scope.iter().map(|&var| values[var]).collect()
+    }
+
+    const STRATEGIES: [Strategy; 4] =
+        [Strategy::Backtrack, Strategy::ForwardCheck, Strategy::MostConstrained, Strategy::Maintain];
+
+    /// Solves `csp` with every strategy and compares with trying every
+    /// assignment against `ok`.
+    fn check<F: Fn(&[usize]) -> bool>(csp: &mut Csp, domains: &[Vec<usize>], ok: F) {
+        let expected = assignments(domains).iter().any(|values| ok(values));
+        for &strategy in STRATEGIES.iter() {
+            match csp.strategy(strategy).solve() {
+                Some(solution) => {
+                    assert!(expected, "found a solution to an unsatisfiable problem");
+                    assert!(ok(&solution), "solution {:?} breaks a constraint", solution);
+                    for (value, domain) in solution.iter().zip(domains.iter()) {
+                        assert!(domain.contains(value));
+                    }
+                },
+                None => assert!(!expected, "missed a solution"),
+            }
+        }
+    }
+
+    fn with_domains(domains: &[Vec<usize>]) -> Csp {
+        let mut csp = Csp::new();
+        for domain in domains.iter() {
+            csp.variable(domain);
+        }
+        csp
+    }
+
+    #[test]
+    fn not_equal_matches_brute_force() {
+        let mut rng = Rng::new(1);
+        for _ in 0..300 {
+            let vars = 1 + rng.below(6);
+            let domains = random_domains(&mut rng, vars);
+            let pairs: Vec<(usize, usize)> = (0..vars)
+                .flat_map(|a| (a + 1..vars).map(move |b| (a, b)))
+                .filter(|_| rng.chance(0.5))
+                .collect();
+            let mut csp = with_domains(&domains);
+            for &(a, b) in pairs.iter() {
+                csp.constrain(NotEqual::new(a, b));
+            }
+            check(&mut csp, &domains, |values| pairs.iter().all(|&(a, b)| values[a] != values[b]));
+        }
+    }
+
+    #[test]
+    fn all_different_matches_brute_force() {
+        let mut rng = Rng::new(2);
+        for _ in 0..300 {
+            let vars = 1 + rng.below(6);
+            let domains = random_domains(&mut rng, vars);
+            let scopes: Vec<Vec<usize>> = (0..1 + rng.below(3)).map(|_| random_scope(&mut rng, vars, 5)).collect();
+            let mut csp = with_domains(&domains);
+            for scope in scopes.iter() {
+                csp.constrain(AllDifferent::new(scope));
+            }
+            check(&mut csp, &domains, |values| {
+                scopes.iter().all(|scope| {
+                    let mut seen = project(values, scope);
+                    seen.sort();
+                    seen.dedup();
+                    seen.len() == scope.len()
+                })
+            });
+        }
+    }
+
+    #[test]
+    fn all_different_counts_values() {
+        // Four variables cannot take three values apart.
+        let mut csp = Csp::new();
+        let vars: Vec<usize> = (0..4).map(|_| csp.variable(&[1, 2, 3])).collect();
+        csp.constrain(AllDifferent::new(&vars));
+        assert_eq!(csp.solve(), None);
+        assert_eq!(csp.explored, 0);
+    }
+
+    #[test]
+    fn table_matches_brute_force() {
+        let mut rng = Rng::new(3);
+        for _ in 0..300 {
+            let vars = 1 + rng.below(6);
+            let domains = random_domains(&mut rng, vars);
+            let tables: Vec<(Vec<usize>, Vec<Vec<usize>>)> = (0..1 + rng.below(3))
+                .map(|_| {
+                    let scope = random_scope(&mut rng, vars, 3);
+                    let tuples = (0..rng.below(8))
+                        .map(|_| (0..scope.len()).map(|_| rng.below(4)).collect())
+                        .collect();
+                    (scope, tuples)
+                })
+                .collect();
+            let mut csp = with_domains(&domains);
+            for (scope, tuples) in tables.iter() {
+                csp.constrain(Table::new(scope, tuples.clone()));
+            }
+            check(&mut csp, &domains, |values| {
+                tables.iter().all(|(scope, tuples)| tuples.contains(&project(values, scope)))
+            });
+        }
+    }
+
+    #[test]
+    fn predicate_matches_brute_force() {
+        let mut rng = Rng::new(4);
+        for _ in 0..300 {
+            let vars = 1 + rng.below(6);
+            let domains = random_domains(&mut rng, vars);
+            // The sum of each scope has a given remainder.
+            let sums: Vec<(Vec<usize>, usize, usize)> = (0..1 + rng.below(3))
+                .map(|_| (random_scope(&mut rng, vars, 3), 2 + rng.below(3), rng.below(2)))
+                .collect();
+            let mut csp = with_domains(&domains);
+            for &(ref scope, modulus, remainder) in sums.iter() {
+                csp.constrain(Predicate::new(scope, move |values: &[usize]| {
+                    values.iter().sum::<usize>() % modulus == remainder
+                }));
+            }
+            check(&mut csp, &domains, |values| {
+                sums.iter().all(|&(ref scope, modulus, remainder)| {
+                    project(values, scope).iter().sum::<usize>() % modulus == remainder
+                })
+            });
+        }
+    }
+
+    #[test]
+    fn mixed_constraints_match_brute_force() {
+        let mut rng = Rng::new(5);
+        for _ in 0..300 {
+            let vars = 2 + rng.below(5);
+            let domains = random_domains(&mut rng, vars);
+            let (a, b) = (rng.below(vars), rng.below(vars));
+            let distinct = random_scope(&mut rng, vars, 3);
+            let scope = random_scope(&mut rng, vars, 2);
+            let tuples: Vec<Vec<usize>> = (0..4)
+                .map(|_| (0..scope.len()).map(|_| rng.below(4)).collect())
+                .collect();
+            let mut csp = with_domains(&domains);
+            csp.constrain(NotEqual::new(a, b))
+                .constrain(AllDifferent::new(&distinct))
+                .constrain(Table::new(&scope, tuples.clone()))
+                .constrain(Predicate::new(&[a, b], |values: &[usize]| values[0] <= values[1] + 1));
+            check(&mut csp, &domains, |values| {
+                let mut seen = project(values, &distinct);
+                seen.sort();
+                seen.dedup();
+                values[a] != values[b] && seen.len() == distinct.len()
+                    && tuples.contains(&project(values, &scope)) && values[a] <= values[b] + 1
+            });
+        }
+    }
+
+    #[test]
+    fn interchangeable_values_keep_a_solution() {
+        // Renaming values 1..k keeps not-equal constraints satisfied, so
+        // fixing the first variable to 1 loses no solution either.
+        let mut rng = Rng::new(7);
+        for _ in 0..300 {
+            let vars = 1 + rng.below(6);
+            let k = 1 + rng.below(4);
+            let domains = vec![(1..k + 1).collect::<Vec<usize>>(); vars];
+            let pairs: Vec<(usize, usize)> = (0..vars)
+                .flat_map(|a| (a + 1..vars).map(move |b| (a, b)))
+                .filter(|_| rng.chance(0.5))
+                .collect();
+            let mut csp = with_domains(&domains);
+            for &(a, b) in pairs.iter() {
+                csp.constrain(NotEqual::new(a, b));
+            }
+            let fixed = rng.chance(0.5);
+            if fixed {
+                csp.fix(0, 1).interchangeable(1);
+            } else {
+                csp.interchangeable(0);
+            }
+            check(&mut csp, &domains, |values| {
+                pairs.iter().all(|&(a, b)| values[a] != values[b]) && (!fixed || values[0] == 1)
+            });
+        }
+    }
+
+    #[test]
+    fn fixing_a_value_outside_the_domain_fails() {
+        let mut csp = Csp::new();
+        csp.variable(&[1, 2]);
+        csp.fix(0, 3);
+        assert_eq!(csp.solve(), None);
+    }
+
+    #[test]
+    fn queens() {
+        for n in 1..9 {
+            for &strategy in STRATEGIES.iter() {
+                let mut csp = Csp::new();
+                csp.strategy(strategy);
+                let rows: Vec<usize> = (0..n).map(|_| csp.variable(&(0..n).collect::<Vec<_>>())).collect();
+                csp.constrain(AllDifferent::new(&rows));
+                for i in 0..n {
+                    for j in i + 1..n {
+                        csp.constrain(Predicate::new(&[rows[i], rows[j]], move |values: &[usize]| {
+                            values[0] + j != values[1] + i && values[0] + i != values[1] + j
+                        }));
+                    }
+                }
+                match csp.solve() {
+                    Some(queens) => {
+                        assert!(n != 2 && n != 3);
+                        for i in 0..n {
+                            for j in i + 1..n {
+                                assert_ne!(queens[i], queens[j]);
+                                assert_ne!(queens[i] + j, queens[j] + i);
+                                assert_ne!(queens[i] + i, queens[j] + j);
+                            }
+                        }
+                    },
+                    None => assert!(n == 2 || n == 3),
+                }
+            }
+        }
+    }
+}
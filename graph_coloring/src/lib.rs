@@ -0,0 +1,10 @@
+// The arithmetic and the generator come from cnf_satisfiability, so there
+// is a single copy of each to maintain.
+#[path = "../../cnf_satisfiability/src/bignum.rs"]
+pub mod bignum;
+pub mod csp;
+#[path = "../../cnf_satisfiability/src/rng.rs"]
+pub mod rng;
+pub mod state;
+
+pub use state::{Algorithm, Fault, Graph, Reduction};
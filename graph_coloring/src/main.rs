@@ -1,20 +1,47 @@
-mod state;
-use state::{Algorithm, Graph};
+extern crate cue6_04;
+
+use cue6_04::{Algorithm, Graph};
 
 use std::env;
+use std::fs::File;
 use std::io;
-use std::io::BufRead;
+use std::io::{BufRead, BufReader};
 
 fn usage(tar: &str) {
     match tar {
         "prog" => {
-            println!("Usage: cue6_04 ALGORITHM NUM_COLORS
+            println!("Usage: cue6_04 ALGORITHM [NUM_COLORS] [OPTIONS]
+                      cue6_04 --cnf NUM_COLORS [--symmetry]
+                      cue6_04 --decode MODEL NUM_COLORS
+                      cue6_04 --enumerate NUM_COLORS [--up-to-permutation]
+                      cue6_04 --count NUM_COLORS [--up-to-permutation]
+                      Without NUM_COLORS, finds the chromatic number.
+                      Reads a DIMACS graph on stdin, where f NODE COLOR fixes the color of a
+                      node and a NODE COLOR... lists the colors it may take.
                       Available algorithms are: dfs - Depth first search
                                                 fc  - Forward checking
-                                                mcv - Most constrained variable")
+                                                mcv - Most constrained variable
+                                                dsatur - DSatur branch and bound
+                                                mac - Maintaining arc consistency (AC-3)
+                                                cbj - Conflict-directed backjumping
+                                                tabucol - Tabu search (incomplete)
+                                                minconf - Min-conflicts with random walk (incomplete)
+                                                csp - Generic CSP engine
+                      Options: --seed N        Seed for the local search algorithms
+                               --iterations N  Moves the local search algorithms may make
+                               --cnf           Write the coloring problem as DIMACS CNF instead of solving
+                               --symmetry      Fix the colors of a clique in the CNF or for dfs, fc and mcv
+                               --decode MODEL  Turn a SAT solver's model of that CNF into a coloring
+                               --dot FILE      Also draw the colored graph in Graphviz DOT
+                               --enumerate     Print every coloring as a v line
+                               --count         Count the colorings, the chromatic polynomial at NUM_COLORS
+                               --up-to-permutation  Count colorings differing only by renaming colors once
+                               --stats         Report how the graph was shrunk before the search")
         },
         "p" => println!("Usage: p edge NODES EDGES"),
         "e" => println!("Usage: e NODE1 NODE2"),
+        "f" => println!("Usage: f NODE COLOR"),
+        "a" => println!("Usage: a NODE COLOR..."),
         _ => println!("Unknown usage string"),
     }
 }
@@ -25,12 +52,12 @@ fn read_stdin() -> Graph {
     for line in stdin.lock().lines() {
         let line = line.unwrap();
         let words: Vec<&str> = line.split_whitespace().collect();
-        let directive = match words.get(0) {
+        let directive = match words.first() {
             Some(word) => word,
             None => continue,
         };
 
-        match directive.as_ref() {
+        match *directive {
             "c" => continue,
             "p" => {
                 match words.get(1) {
@@ -54,6 +81,26 @@ fn read_stdin() -> Graph {
 
                 graph.connect(node1, node2);
             },
+            "f" => {
+                if words.len() != 3 {
+                    usage("f");
+                    continue
+                }
+                let node = words.get(1).unwrap().parse::<usize>().unwrap();
+                let color = words.get(2).unwrap().parse::<usize>().unwrap();
+
+                graph.fix(node, color);
+            },
+            "a" => {
+                if words.len() < 2 {
+                    usage("a");
+                    continue
+                }
+                let node = words.get(1).unwrap().parse::<usize>().unwrap();
+                let colors: Vec<usize> = words[2..].iter().map(|word| word.parse().unwrap()).collect();
+
+                graph.allow(node, &colors);
+            },
             x => {
                 println!("Unknown directive {}", x);
                 continue
@@ -64,21 +111,144 @@ fn read_stdin() -> Graph {
     graph
 }
 
-fn main() {
-    let argv: Vec<String> = env::args().collect();
-    if argv.len() != 3 {
-        usage("prog");
-        return;
+struct Options {
+    algorithm: Option<Algorithm>,
+    num_colors: Option<usize>,
+    seed: u64,
+    iterations: Option<usize>,
+    cnf: bool,
+    symmetry: bool,
+    decode: Option<String>,
+    dot: Option<String>,
+    enumerate: bool,
+    count: bool,
+    up_to_permutation: bool,
+    stats: bool,
+}
+
+fn parse_args() -> Option<Options> {
+    let mut options = Options {
+        algorithm: None,
+        num_colors: None,
+        seed: 0,
+        iterations: None,
+        cnf: false,
+        symmetry: false,
+        decode: None,
+        dot: None,
+        enumerate: false,
+        count: false,
+        up_to_permutation: false,
+        stats: false,
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => options.seed = args.next()?.parse().ok()?,
+            "--iterations" => options.iterations = Some(args.next()?.parse().ok()?),
+            "--cnf" => options.cnf = true,
+            "--symmetry" => options.symmetry = true,
+            "--decode" => options.decode = Some(args.next()?),
+            "--dot" => options.dot = Some(args.next()?),
+            "--enumerate" => options.enumerate = true,
+            "--count" => options.count = true,
+            "--up-to-permutation" => options.up_to_permutation = true,
+            "--stats" => options.stats = true,
+            "dfs" => options.algorithm = Some(Algorithm::DepthFirst),
+            "fc" => options.algorithm = Some(Algorithm::ForwardCheck),
+            "mcv" => options.algorithm = Some(Algorithm::MostConstrained),
+            "dsatur" => options.algorithm = Some(Algorithm::DSatur),
+            "mac" => options.algorithm = Some(Algorithm::ArcConsistency),
+            "cbj" => options.algorithm = Some(Algorithm::Backjump),
+            "tabucol" => options.algorithm = Some(Algorithm::Tabucol),
+            "minconf" => options.algorithm = Some(Algorithm::MinConflicts),
+            "csp" => options.algorithm = Some(Algorithm::Csp),
+            _ if options.num_colors.is_none() => options.num_colors = Some(arg.parse().ok()?),
+            _ => return None,
+        }
+    }
+
+    // Exporting, decoding, enumerating and counting need a color count but
+    // no algorithm, and only one of them can run. Statistics come from the
+    // search.
+    let modes = [options.cnf, options.decode.is_some(), options.enumerate, options.count];
+    let modes = modes.iter().filter(|&&mode| mode).count();
+    let listing = options.enumerate || options.count;
+    if modes > 1
+        || modes == 1 && options.num_colors.is_none()
+        || (modes == 1) == options.algorithm.is_some()
+        || (options.cnf || listing) && options.dot.is_some()
+        || options.up_to_permutation && !listing
+        || options.stats && modes == 1 {
+        return None;
     }
+    Some(options)
+}
 
-    let algorithm = match argv[1].as_ref() {
-        "dfs" => Algorithm::DepthFirst,
-        "fc" => Algorithm::ForwardCheck,
-        "mcv" => Algorithm::MostConstrained,
-        _ => { usage("prog"); return; },
+fn main() {
+    let options = match parse_args() {
+        Some(options) => options,
+        None => { usage("prog"); return },
     };
-    let num_colors = argv[2].parse::<usize>().unwrap();
 
     let mut graph = read_stdin();
-    graph.colors(num_colors).colorify(algorithm);
+    graph.seed(options.seed).symmetry(options.symmetry);
+    if let Some(iterations) = options.iterations {
+        graph.iterations(iterations);
+    }
+
+    if options.cnf {
+        graph.colors(options.num_colors.unwrap());
+        let stdout = io::stdout();
+        if let Err(e) = graph.write_cnf(&mut stdout.lock(), options.symmetry) {
+            println!("Could not write the CNF: {}", e);
+        }
+        return;
+    }
+    if options.enumerate {
+        graph.colors(options.num_colors.unwrap());
+        let count = graph.enumerate(options.up_to_permutation, |colors| {
+            let colors: Vec<String> = colors.iter().map(|color| color.to_string()).collect();
+            println!("v {}", colors.join(" "));
+        });
+        println!("s count {}", count);
+        println!("{} branching nodes explored.", graph.explored);
+        return;
+    }
+    if options.count {
+        graph.colors(options.num_colors.unwrap());
+        let count = graph.count_colorings(options.up_to_permutation);
+        println!("s count {}", count);
+        println!("{} branching nodes explored.", graph.explored);
+        return;
+    }
+    if let Some(ref path) = options.decode {
+        graph.colors(options.num_colors.unwrap());
+        let decoded = File::open(path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| graph.decode(BufReader::new(file)));
+        match decoded {
+            Ok(Some(true)) => graph.print_coloring(),
+            Ok(Some(false)) => println!("No Solution."),
+            Ok(None) => println!("No Solution found by the SAT solver, which gave up."),
+            Err(e) => println!("Could not decode {}: {}", path, e),
+        }
+    } else {
+        let algorithm = options.algorithm.unwrap();
+        match options.num_colors {
+            Some(num_colors) => graph.colors(num_colors).colorify(algorithm),
+            None => graph.chromatic(algorithm),
+        }
+        if let (true, Some(reduction)) = (options.stats, graph.reduction) {
+            println!("c {}", reduction);
+        }
+    }
+
+    if let Some(ref path) = options.dot {
+        let written = File::create(path).and_then(|mut file| graph.write_dot(&mut file));
+        if let Err(e) = written {
+            println!("Could not write {}: {}", path, e);
+        }
+    }
 }
@@ -1,17 +1,54 @@
 use std::fmt;
 use std::fmt::{Display, Formatter};
 
+use csp::Strategy;
+
+mod backjump;
+mod chromatic;
+mod cnf;
+mod count;
+mod dot;
+mod dsatur;
+mod generic;
+mod local;
+mod mac;
+mod reduce;
+#[cfg(test)]
+mod testing;
+mod verify;
+
+pub use self::reduce::Reduction;
+pub use self::verify::Fault;
+
+#[derive(Clone, Copy)]
 pub enum Algorithm {
     DepthFirst,
     ForwardCheck,
     MostConstrained,
+    DSatur,
+    ArcConsistency,
+    Backjump,
+    Tabucol,
+    MinConflicts,
+    Csp,
+}
+
+impl Algorithm {
+    /// Whether a failed search proves that no coloring exists.
+    pub fn is_complete(self) -> bool {
+        !matches!(self, Algorithm::Tabucol | Algorithm::MinConflicts)
+    }
 }
 
 struct Node {
     pub color: usize,
     pub index: usize,
     pub neighbors: Vec<usize>,
+    /// Color c at c - 1 if the vertex may take it, and 0 otherwise.
     pub options: Vec<usize>,
+    /// Colors the vertex is limited to, in increasing order, or None if it
+    /// may take any.
+    pub allowed: Option<Vec<usize>>,
 }
 
 impl Node {
@@ -21,8 +58,14 @@ impl Node {
             index: i,
             neighbors: Vec::with_capacity(0),
             options: Vec::with_capacity(0),
+            allowed: None,
         }
     }
+
+    /// Whether the vertex is its own neighbor, which no coloring allows.
+    pub fn has_loop(&self) -> bool {
+        self.neighbors.contains(&self.index)
+    }
 }
 
 pub struct Graph {
@@ -30,7 +73,18 @@ pub struct Graph {
     edges: usize,
     colors: usize,
     pub explored: usize,
+    /// How the last search shrank the graph, if it did.
+    pub reduction: Option<Reduction>,
     nodes: Vec<Node>,
+    seed: u64,
+    iterations: usize,
+    symmetry: bool,
+}
+
+impl Default for Graph {
+    fn default() -> Graph {
+        Graph::new()
+    }
 }
 
 impl Graph {
@@ -40,7 +94,11 @@ impl Graph {
             edges: 0,
             colors: 0,
             explored: 0,
+            reduction: None,
             nodes: Vec::new(),
+            seed: 0,
+            iterations: 1_000_000,
+            symmetry: false,
         }
     }
 
@@ -69,11 +127,57 @@ impl Graph {
         }
 
         for node in self.nodes.iter_mut() {
-            node.options = options.clone();
+            node.options = options.iter()
+                .map(|&color| match node.allowed {
+                    Some(ref allowed) if allowed.binary_search(&color).is_err() => 0,
+                    _ => color,
+                })
+                .collect();
         }
         self
     }
 
+    /// Seed for the random choices of the local search algorithms.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Number of moves the local search algorithms may make.
+    pub fn iterations(&mut self, iterations: usize) -> &mut Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Whether dfs, fc and mcv start from a clique fixed to colors 1, 2, ...
+    pub fn symmetry(&mut self, symmetry: bool) -> &mut Self {
+        self.symmetry = symmetry;
+        self
+    }
+
+    /// Limits node `n` to `colors`, on top of any earlier limit.
+    pub fn allow(&mut self, n: usize, colors: &[usize]) {
+        let node = self.nodes.get_mut(n - 1)
+            .expect("Allowed node doesn't exist");
+        let mut allowed: Vec<usize> = colors.iter()
+            .cloned()
+            .filter(|color| node.allowed.as_ref().is_none_or(|old| old.contains(color)))
+            .collect();
+        allowed.sort();
+        allowed.dedup();
+        for (option, color) in node.options.iter_mut().zip(1..) {
+            if allowed.binary_search(&color).is_err() {
+                *option = 0;
+            }
+        }
+        node.allowed = Some(allowed);
+    }
+
+    /// Precolors node `n`.
+    pub fn fix(&mut self, n: usize, color: usize) {
+        self.allow(n, &[color]);
+    }
+
     pub fn connect(&mut self, n1: usize, n2: usize) {
         {
             let node1: &mut Node = self.nodes.get_mut(n1 - 1)
@@ -81,130 +185,57 @@ impl Graph {
             node1.neighbors.push(n2 - 1);
         }
         {
-            let ref mut node2 = self.nodes.get_mut(n2 - 1)
+            let node2 = self.nodes.get_mut(n2 - 1)
                 .expect("Connect nodes don't exist");
             node2.neighbors.push(n1 - 1);
         }
     }
 
     pub fn colorify(&mut self, alg: Algorithm) {
-        let solved = match alg {
-            Algorithm::DepthFirst => self.color_dfs(0),
-            Algorithm::ForwardCheck => self.color_fc(0),
-            Algorithm::MostConstrained => self.color_mcv(0),
-        };
-        if solved {
+        if self.search(alg) {
             self.print_coloring();
-        } else {
+        } else if alg.is_complete() {
             println!("No Solution.");
+        } else {
+            println!("No Solution found in {} iterations.", self.iterations);
         }
         println!("{} branching nodes explored.", self.explored);
     }
 
-    fn color_dfs(&mut self, index: usize) -> bool {
-        if index >= self.vertices {
-            if self.is_colored() {
-                return true;
-            }
-            return false;
-        }
-
-        for i in 1..self.colors + 1 {
-            self.explored += 1;
-            unsafe { self.nodes.get_unchecked_mut(index).color = i; }
-            if !self.valid(index) {
-                continue;
-            }
-            if self.color_dfs(index + 1) == true {
-                return true;
-            }
+    /// Colors the graph with `alg` and returns the color of every vertex, or
+    /// None if no coloring was found.
+    pub fn find_coloring(&mut self, alg: Algorithm) -> Option<Vec<usize>> {
+        if self.search(alg) {
+            Some(self.nodes.iter().map(|node| node.color).collect())
+        } else {
+            None
         }
-        unsafe { self.nodes.get_unchecked_mut(index).color = 0; }
-        false
     }
 
-    fn color_fc(&mut self, index: usize) -> bool {
-        if index >= self.vertices {
-            if self.is_colored() {
-                return true;
-            }
-            return false;
-        }
-
-        // Being limited by borrow checker
-        let options = self.nodes.get(index).unwrap().options.clone();
-        for color in options.iter() {
-            if *color == 0 {
-                continue;
-            }
-
-
-            self.explored += 1;
-            self.nodes.get_mut(index).unwrap().color = *color;
-            self.alert_neighbors(index, *color);
-            if self.color_fc(index + 1) == true {
-                return true;
-            }
-
-            self.unalert_neighbors(index, *color);
-        }
-        unsafe { self.nodes.get_unchecked_mut(index).color = 0; }
-        false
+    /// Tries to color the graph with the current number of colors.
+    fn search(&mut self, alg: Algorithm) -> bool {
+        self.color_reduced(alg)
     }
 
-    fn color_mcv(&mut self, index: usize) -> bool {
-        if index >= self.vertices {
-            if self.is_colored() {
-                return true;
-            }
-            return false;
-        }
-
-        // Being limited by borrow checker
-        let options = self.nodes.get(index).unwrap().options.clone();
-        for color in options.iter() {
-            if *color == 0 {
-                continue;
-            }
-
-            self.explored += 1;
-            self.nodes.get_mut(index).unwrap().color = *color;
-            let next_i = findmcv(&self.nodes);
-            self.alert_neighbors(index, *color);
-            if self.color_mcv(next_i) == true {
-                return true;
-            }
-
-            self.unalert_neighbors(index, *color);
+    /// Runs `alg` on the whole graph as it is.
+    fn solve(&mut self, alg: Algorithm) -> bool {
+        match alg {
+            Algorithm::DepthFirst => self.color_search(Strategy::Backtrack),
+            Algorithm::ForwardCheck => self.color_search(Strategy::ForwardCheck),
+            Algorithm::MostConstrained => self.color_search(Strategy::MostConstrained),
+            Algorithm::DSatur => self.color_dsatur(),
+            Algorithm::ArcConsistency => self.color_mac(),
+            Algorithm::Backjump => self.color_cbj(),
+            Algorithm::Tabucol => self.color_tabucol(),
+            Algorithm::MinConflicts => self.color_min_conflicts(),
+            Algorithm::Csp => self.color_csp(),
         }
-        unsafe { self.nodes.get_unchecked_mut(index).color = 0; }
-        false
     }
 
-    fn print_coloring(&self) {
+    pub fn print_coloring(&self) {
         println!("s col {}", self.colors);
-        let mut node_num = 1;
-        for node in self.nodes.iter() {
+        for (node_num, node) in (1..).zip(self.nodes.iter()) {
             println!("l {} {}", node_num, node.color);
-            node_num += 1;
-        }
-    }
-
-    fn alert_neighbors(&mut self, i: usize, color: usize) {
-        let neighbors = self.nodes.get_mut(i).unwrap().neighbors.clone();
-        for neigh in neighbors.iter() {
-            let neigh_node = self.nodes.get_mut(*neigh).unwrap();
-            let ref mut opts = neigh_node.options;
-            opts[color - 1] = 0;
-        }
-    }
-
-    fn unalert_neighbors(&mut self, i: usize, color: usize) {
-        let neighbors = self.nodes.get_mut(i).unwrap().neighbors.clone();
-        for neigh in neighbors.iter() {
-            let neigh_node = self.nodes.get_mut(*neigh).unwrap();
-            let ref mut opts = neigh_node.options;
-            opts[color - 1] = color;
         }
     }
 
@@ -223,55 +254,81 @@ impl Graph {
         true
     }
 
-    fn is_colored(&self) -> bool {
-        for node in self.nodes.iter() {
-            if node.color == 0 {
-                return false;
-            }
-        }
-        true
+    /// Whether some vertex may not take every color.
+    fn restricted(&self) -> bool {
+        self.nodes.iter().any(|node| node.options.contains(&0))
     }
 }
 
 impl Display for Graph {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        let mut i = 1;
         let mut out = String::new();
-        for node in self.nodes.iter() {
+        for (i, node) in (1..).zip(self.nodes.iter()) {
             for neighbor in node.neighbors.iter() {
                 out.push_str(&format!("({}->{})", i, neighbor));
             }
-            i += 1;
         }
-        f.write_str(&*out)
+        f.write_str(&out)
     }
 }
 
-fn findmcv(nodes: &Vec<Node>) -> usize {
-    use std::usize;
-    let mut mcv = usize::MAX;
-    let mut min_options = usize::MAX;
-    let mut index = 0;
-    for node in nodes {
-        if node.color != 0 {
-            // Already colored.
-            index += 1;
-            continue;
+#[cfg(test)]
+mod tests {
+    use super::testing::{check_algorithm, Instance};
+    use super::Algorithm;
+
+    #[test]
+    fn searches_match_brute_force_with_and_without_symmetry() {
+        for &symmetry in [false, true].iter() {
+            check_algorithm(Algorithm::DepthFirst, 10, symmetry);
+            check_algorithm(Algorithm::ForwardCheck, 11, symmetry);
+            check_algorithm(Algorithm::MostConstrained, 12, symmetry);
         }
-        // Count the number of zeros in a node.
-        let mut total_options = 0;
-        for opt in node.options.iter() {
-            if *opt != 0 {
-                total_options += 1;
-            }
+    }
+
+    #[test]
+    fn symmetry_cuts_the_refutation_short() {
+        // The odd wheel W7 needs four colors; with three, each search has
+        // to try every way of coloring it before it gives up.
+        let mut edges: Vec<(usize, usize)> = (0..7).map(|v| (v, (v + 1) % 7)).collect();
+        edges.extend((0..7).map(|v| (v, 7)));
+        let instance = Instance { vertices: 8, edges, allowed: vec![None; 8] };
+        for &alg in [Algorithm::DepthFirst, Algorithm::ForwardCheck, Algorithm::MostConstrained].iter() {
+            let mut plain = instance.graph(3);
+            assert!(!plain.solve(alg));
+            let mut fixed = instance.graph(3);
+            fixed.symmetry(true);
+            assert!(!fixed.solve(alg));
+            assert!(fixed.explored < plain.explored);
         }
+    }
 
-        if total_options < min_options {
-            mcv = index;
-            min_options = total_options;
+    #[test]
+    fn lists_narrow_each_other_and_survive_a_color_change() {
+        let mut early = Instance { vertices: 1, edges: Vec::new(), allowed: vec![None] }.graph(0);
+        early.allow(1, &[3, 1, 2, 3]);
+        early.allow(1, &[4, 2, 3]);
+        early.colors(4);
+        let mut late = Instance { vertices: 1, edges: Vec::new(), allowed: vec![None] }.graph(4);
+        late.allow(1, &[1, 2, 3]);
+        late.allow(1, &[2, 3, 4]);
+        for graph in [early, late].iter() {
+            assert_eq!(graph.nodes[0].allowed, Some(vec![2, 3]));
+            assert_eq!(graph.nodes[0].options, vec![0, 2, 3, 0]);
         }
-        index += 1;
     }
 
-    mcv
+    #[test]
+    fn every_complete_algorithm_keeps_fixed_colors() {
+        // Both ends of a path fixed to the same color, which no search
+        // would pick first on its own.
+        let instance = Instance { vertices: 3, edges: vec![(0, 1), (1, 2)], allowed: vec![Some(vec![3]), None, Some(vec![3])] };
+        for &alg in [Algorithm::DepthFirst, Algorithm::ForwardCheck, Algorithm::MostConstrained, Algorithm::DSatur,
+                     Algorithm::ArcConsistency, Algorithm::Backjump, Algorithm::Csp].iter() {
+            let mut graph = instance.graph(3);
+            graph.fix(2, 2);
+            assert!(graph.solve(alg));
+            assert_eq!(graph.coloring(), vec![3, 2, 3]);
+        }
+    }
 }
@@ -0,0 +1,91 @@
+use std::collections::BTreeSet;
+
+use super::Graph;
+
+impl Graph {
+    /// Depth first search in vertex order with conflict-directed
+    /// backjumping. Every vertex collects the earlier vertices that ruled
+    /// out its colors; once they are exhausted, search returns straight to
+    /// the latest of them instead of the previous vertex.
+    pub fn color_cbj(&mut self) -> bool {
+        // Limited vertices make colors distinguishable.
+        let used = if self.restricted() { self.colors } else { 0 };
+        self.cbj_search(0, used).is_ok()
+    }
+
+    /// Returns the conflict set of a failed subtree: the vertices whose
+    /// colors caused the failure. As in dfs, only the `used` colors and one
+    /// new one are tried. That needs no extra blame: an untried color is
+    /// unused so far, like the new one, and swapping the two turns any
+    /// coloring with it into one the search has already ruled out.
+    fn cbj_search(&mut self, index: usize, used: usize) -> Result<(), BTreeSet<usize>> {
+        if index >= self.vertices {
+            return Ok(());
+        }
+
+        let mut conflicts = BTreeSet::new();
+        if self.nodes[index].has_loop() {
+            // No color works, whatever the earlier vertices do.
+            return Err(conflicts);
+        }
+        for color in 1..self.colors.min(used + 1) + 1 {
+            if self.nodes[index].options[color - 1] == 0 {
+                // Not allowed whatever the other vertices do.
+                continue;
+            }
+            self.explored += 1;
+            // Blame the earliest neighbor with this color, which allows the
+            // longest jumps.
+            let culprit = self.nodes[index].neighbors.iter()
+                .cloned()
+                .filter(|&u| u < index && self.nodes[u].color == color)
+                .min();
+            if let Some(culprit) = culprit {
+                conflicts.insert(culprit);
+                continue;
+            }
+
+            self.nodes[index].color = color;
+            match self.cbj_search(index + 1, used.max(color)) {
+                Ok(()) => return Ok(()),
+                Err(below) => {
+                    if !below.contains(&index) {
+                        // This vertex played no part in the failure.
+                        self.nodes[index].color = 0;
+                        return Err(below);
+                    }
+                    conflicts.extend(below.into_iter().filter(|&u| u != index));
+                },
+            }
+        }
+        self.nodes[index].color = 0;
+        Err(conflicts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use state::testing::{check_algorithm, Instance};
+    use Algorithm;
+
+    #[test]
+    fn backjumping_matches_brute_force() {
+        check_algorithm(Algorithm::Backjump, 4, false);
+    }
+
+    #[test]
+    fn jumps_over_unrelated_vertices() {
+        // A K4 that cannot take 3 colors, after a long path it has nothing
+        // to do with: backjumping never retries the path.
+        let mut edges: Vec<(usize, usize)> = (0..19).map(|v| (v, v + 1)).collect();
+        for u in 20..24 {
+            for v in u + 1..24 {
+                edges.push((u, v));
+            }
+        }
+        let instance = Instance { vertices: 24, edges, allowed: vec![None; 24] };
+        let mut graph = instance.graph(3);
+        assert!(!graph.color_cbj());
+        assert!(graph.explored < 100);
+    }
+}
@@ -0,0 +1,194 @@
+use super::{Algorithm, Graph};
+
+impl Graph {
+    /// A clique found greedily from every starting vertex in turn; the
+    /// largest one is kept. Its size is a lower bound on the number of colors.
+    pub fn greedy_clique(&self) -> Vec<usize> {
+        let words = self.nodes.len().div_ceil(64);
+        let mut adjacent = vec![vec![0u64; words]; self.nodes.len()];
+        for node in self.nodes.iter() {
+            for &v in node.neighbors.iter() {
+                if v != node.index {
+                    adjacent[node.index][v / 64] |= 1 << (v % 64);
+                }
+            }
+        }
+        let common = |a: &[u64], b: &[u64]| -> usize {
+            a.iter().zip(b.iter()).map(|(x, y)| (x & y).count_ones() as usize).sum()
+        };
+
+        let mut best = Vec::new();
+        for start in 0..self.nodes.len() {
+            let mut clique = vec![start];
+            let mut candidates = adjacent[start].clone();
+            loop {
+                // Keep the vertex that leaves the most candidates behind.
+                let next = (0..self.nodes.len())
+                    .filter(|&v| candidates[v / 64] >> (v % 64) & 1 == 1)
+                    .max_by_key(|&v| common(&candidates, &adjacent[v]));
+                let next = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+                clique.push(next);
+                for (word, adj) in candidates.iter_mut().zip(adjacent[next].iter()) {
+                    *word &= adj;
+                }
+            }
+            if clique.len() > best.len() {
+                best = clique;
+            }
+        }
+        best
+    }
+
+    /// Finds the chromatic number. Greedy DSatur gives an upper bound and a
+    /// clique a lower bound; decision calls with `alg` then try one color
+    /// fewer than the best coloring so far until one fails. Only a complete
+    /// `alg` proves the result optimal. When color lists defeat the greedy
+    /// pass, colors are counted up from the clique instead. A self-loop
+    /// rules out every number of colors, so it ends the search at once.
+    pub fn chromatic(&mut self, alg: Algorithm) {
+        if let Some(node) = self.nodes.iter().find(|node| node.has_loop()) {
+            println!("c vertex {} has a self-loop", node.index + 1);
+            println!("No Solution.");
+            println!("{} branching nodes explored.", self.explored);
+            return;
+        }
+        let clique = self.greedy_clique();
+        let mut lower = clique.len();
+        let clique: Vec<String> = clique.iter().map(|v| (v + 1).to_string()).collect();
+        println!("c clique {}", clique.join(" "));
+        let mut best = match self.dsatur_greedy() {
+            Some(best) => best,
+            None => {
+                println!("c greedy coloring does not fit the color lists");
+                match self.first_coloring(alg, lower) {
+                    Some(best) => {
+                        if alg.is_complete() {
+                            lower = best.iter().cloned().max().unwrap_or(0);
+                        }
+                        best
+                    },
+                    None => {
+                        println!("No Solution.");
+                        println!("{} branching nodes explored.", self.explored);
+                        return;
+                    },
+                }
+            },
+        };
+        let mut upper = best.iter().cloned().max().unwrap_or(0);
+        println!("c bounds {} {}", lower, upper);
+
+        while upper > lower {
+            for node in self.nodes.iter_mut() {
+                node.color = 0;
+            }
+            self.colors(upper - 1);
+            if !self.search(alg) {
+                if !alg.is_complete() {
+                    println!("c no {}-coloring found, {} colors are not proven optimal",
+                             upper - 1, upper);
+                }
+                break;
+            }
+            best = self.nodes.iter().map(|node| node.color).collect();
+            upper = best.iter().cloned().max().unwrap_or(0);
+        }
+
+        for (node, color) in self.nodes.iter_mut().zip(best) {
+            node.color = color;
+        }
+        self.colors = upper;
+        self.print_coloring();
+        println!("{} branching nodes explored.", self.explored);
+    }
+
+    /// Colors with `alg` using `from` colors, then one more each time it
+    /// fails, up to the most colors a coloring could need: the largest
+    /// listed color, plus a color of its own for each vertex without a list.
+    fn first_coloring(&mut self, alg: Algorithm, from: usize) -> Option<Vec<usize>> {
+        let listed = self.nodes.iter()
+            .filter_map(|node| node.allowed.as_ref().and_then(|allowed| allowed.last()))
+            .cloned()
+            .max()
+            .unwrap_or(0);
+        let unlisted = self.nodes.iter().filter(|node| node.allowed.is_none()).count();
+        for colors in from..listed + unlisted + 1 {
+            for node in self.nodes.iter_mut() {
+                node.color = 0;
+            }
+            self.colors(colors);
+            if self.search(alg) {
+                return Some(self.nodes.iter().map(|node| node.color).collect());
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rng::Rng;
+    use state::testing::Instance;
+    use Algorithm;
+
+    #[test]
+    fn finds_the_chromatic_number() {
+        let mut rng = Rng::new(1);
+        for _ in 0..200 {
+            let instance = Instance::random(&mut rng, 8, true, true);
+            let least = (0..10).find(|&k| instance.colorable(k));
+            for &alg in [Algorithm::DSatur, Algorithm::Backjump].iter() {
+                let mut graph = instance.graph(0);
+                graph.chromatic(alg);
+                match least {
+                    Some(k) => {
+                        assert_eq!(graph.colors, k);
+                        assert!(instance.is_coloring(&graph.coloring(), k));
+                    },
+                    None => assert!(graph.coloring().iter().all(|&color| color == 0)),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn gives_up_at_once_on_self_loops_and_lists() {
+        // A self-loop ends the search before any branching.
+        let mut looped = Instance::random(&mut Rng::new(3), 30, false, false);
+        looped.edges.push((4, 4));
+        let mut graph = looped.graph(0);
+        graph.chromatic(Algorithm::DSatur);
+        assert_eq!(graph.explored, 0);
+        assert!(graph.coloring().iter().all(|&color| color == 0));
+        // A 12-clique choosing from colors 1 to 3 needs no search beyond 3.
+        let n = 12;
+        let edges = (0..n).flat_map(|u| (u + 1..n).map(move |v| (u, v))).collect();
+        let mut graph = Instance { vertices: n, edges, allowed: vec![Some(vec![1, 2, 3]); n] }.graph(0);
+        graph.chromatic(Algorithm::DSatur);
+        assert_eq!(graph.explored, 0);
+        assert!(graph.coloring().iter().all(|&color| color == 0));
+    }
+
+    #[test]
+    fn greedy_clique_is_a_clique() {
+        let mut rng = Rng::new(2);
+        for _ in 0..200 {
+            let instance = Instance::random(&mut rng, 12, true, false);
+            let clique = instance.graph(0).greedy_clique();
+            assert!(!clique.is_empty());
+            for (i, &u) in clique.iter().enumerate() {
+                for &v in clique[i + 1..].iter() {
+                    assert!(instance.edges.contains(&(u.min(v), u.max(v))));
+                }
+            }
+        }
+        for n in 1..8 {
+            let edges = (0..n).flat_map(|u| (u + 1..n).map(move |v| (u, v))).collect();
+            let instance = Instance { vertices: n, edges, allowed: vec![None; n] };
+            assert_eq!(instance.graph(0).greedy_clique().len(), n);
+        }
+    }
+}
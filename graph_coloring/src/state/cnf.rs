@@ -0,0 +1,217 @@
+use std::io;
+use std::io::{BufRead, Write};
+
+use super::Graph;
+
+impl Graph {
+    /// DIMACS variable saying vertex `v` (from 0) has color `c` (from 1).
+    fn variable(&self, v: usize, c: usize) -> usize {
+        v * self.colors + c
+    }
+
+    /// Writes the k-coloring problem as CNF with one variable per vertex and
+    /// color: every vertex gets at least one and at most one of its allowed
+    /// colors, and the ends of an edge never share one. With `symmetry`, the
+    /// vertices of a greedy clique are fixed to colors 1, 2, ... if colors
+    /// are interchangeable.
+    pub fn write_cnf<W: Write>(&self, out: &mut W, symmetry: bool) -> io::Result<()> {
+        let k = self.colors;
+        let mut clauses: Vec<Vec<isize>> = Vec::new();
+        for v in 0..self.vertices {
+            clauses.push((1..k + 1).map(|c| self.variable(v, c) as isize).collect());
+            for c in 1..k + 1 {
+                for d in c + 1..k + 1 {
+                    clauses.push(vec![-(self.variable(v, c) as isize), -(self.variable(v, d) as isize)]);
+                }
+            }
+        }
+        for node in self.nodes.iter() {
+            for &u in node.neighbors.iter().filter(|&&u| u > node.index) {
+                for c in 1..k + 1 {
+                    clauses.push(vec![-(self.variable(node.index, c) as isize),
+                                      -(self.variable(u, c) as isize)]);
+                }
+            }
+        }
+        for node in self.nodes.iter() {
+            for (c, &option) in (1..).zip(node.options.iter()) {
+                // A self-loop rules out every color.
+                if option == 0 || node.has_loop() {
+                    clauses.push(vec![-(self.variable(node.index, c) as isize)]);
+                }
+            }
+        }
+        // Limited vertices make colors distinguishable.
+        if symmetry && !self.restricted() {
+            for (i, &v) in self.greedy_clique().iter().enumerate() {
+                if i < k {
+                    clauses.push(vec![self.variable(v, i + 1) as isize]);
+                } else {
+                    // The clique alone needs more colors than there are.
+                    clauses.push(Vec::new());
+                    break;
+                }
+            }
+        }
+
+        writeln!(out, "c vertex V has color C when variable (V - 1) * {} + C is true", k)?;
+        writeln!(out, "p cnf {} {}", self.vertices * k, clauses.len())?;
+        for clause in clauses.iter() {
+            for lit in clause.iter() {
+                write!(out, "{} ", lit)?;
+            }
+            writeln!(out, "0")?;
+        }
+        Ok(())
+    }
+
+    /// Reads a SAT solver's answer to the problem from `write_cnf` and
+    /// colors the graph with it. Accepts `v` lines as well as bare literal
+    /// lines and skips other output. Returns Some(false) if the solver said
+    /// it found no model and None if it gave up without an answer. An answer
+    /// with neither a status nor a model, or a model that is no proper
+    /// coloring, is an error.
+    pub fn decode<R: BufRead>(&mut self, model: R) -> Result<Option<bool>, String> {
+        let mut value = vec![false; self.vertices * self.colors + 1];
+        let mut found = false;
+        for line in model.lines() {
+            let line = line.map_err(|e| e.to_string())?;
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let lits = match words.first() {
+                None | Some(&"c") => continue,
+                Some(&"s") => {
+                    let status = &words[1..];
+                    if status == ["UNSATISFIABLE"] || status.len() == 4 && status[1] == "0" {
+                        return Ok(Some(false));
+                    }
+                    if status == ["UNKNOWN"] || status == ["INDETERMINATE"] || status.len() == 4 && status[1] == "-1" {
+                        return Ok(None);
+                    }
+                    continue;
+                },
+                Some(&"UNSAT") => return Ok(Some(false)),
+                Some(&"INDET") => return Ok(None),
+                Some(&"SAT") => continue,
+                Some(&"v") => &words[1..],
+                // Anything else a solver says about its run.
+                Some(_) if words.iter().any(|word| word.parse::<isize>().is_err()) => continue,
+                Some(_) => &words[..],
+            };
+            for word in lits {
+                let lit = match word.parse::<isize>() {
+                    Ok(lit) => lit,
+                    Err(_) => return Err(format!("Unknown {}", word)),
+                };
+                found = true;
+                let var = lit.unsigned_abs();
+                if lit > 0 && var < value.len() {
+                    value[var] = true;
+                }
+            }
+        }
+        if !found {
+            return Err("no model or status line".to_string());
+        }
+
+        for v in 0..self.vertices {
+            let color = (1..self.colors + 1).find(|&c| value[self.variable(v, c)]);
+            self.nodes[v].color = match color {
+                Some(color) => color,
+                None => return Err(format!("Vertex {} has no color in the model", v + 1)),
+            };
+        }
+        let faults: Vec<String> = self.faults().iter().map(|fault| fault.to_string()).collect();
+        if !faults.is_empty() {
+            return Err(format!("the model is no coloring: {}", faults.join(", ")));
+        }
+        Ok(Some(true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rng::Rng;
+    use state::testing::Instance;
+
+    /// The clauses of a DIMACS CNF and its number of variables.
+    fn read(text: &[u8]) -> (usize, Vec<Vec<isize>>) {
+        let text = String::from_utf8(text.to_vec()).unwrap();
+        let mut vars = 0;
+        let mut clauses = Vec::new();
+        for line in text.lines() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[0] {
+                "c" => {},
+                "p" => vars = words[2].parse().unwrap(),
+                _ => {
+                    let lits: Vec<isize> = words.iter().map(|word| word.parse().unwrap()).collect();
+                    assert_eq!(lits.last(), Some(&0));
+                    clauses.push(lits[..lits.len() - 1].to_vec());
+                },
+            }
+        }
+        (vars, clauses)
+    }
+
+    /// Every assignment satisfying `clauses`, as the true variables.
+    fn models(vars: usize, clauses: &[Vec<isize>]) -> Vec<Vec<isize>> {
+        (0..1u64 << vars)
+            .filter(|&bits| {
+                clauses.iter().all(|clause| clause.iter().any(|&lit| (bits >> (lit.unsigned_abs() - 1) & 1 == 1) == (lit > 0)))
+            })
+            .map(|bits| (1..vars as isize + 1).filter(|&var| bits >> (var - 1) & 1 == 1).collect())
+            .collect()
+    }
+
+    #[test]
+    fn models_are_the_colorings() {
+        let mut rng = Rng::new(1);
+        for _ in 0..300 {
+            let instance = Instance::random(&mut rng, 5, true, true);
+            let k = rng.below(4);
+            let mut graph = instance.graph(k);
+            for &symmetry in [false, true].iter() {
+                let mut text = Vec::new();
+                graph.write_cnf(&mut text, symmetry).unwrap();
+                let (vars, clauses) = read(&text);
+                assert_eq!(vars, instance.vertices * k);
+                let models = models(vars, &clauses);
+                if symmetry {
+                    assert_eq!(models.is_empty(), !instance.colorable(k));
+                } else {
+                    assert_eq!(models.len() as u64, instance.colorings(k));
+                }
+                for model in models.iter() {
+                    let lits: Vec<String> = model.iter().map(|lit| lit.to_string()).collect();
+                    let answer = format!("s SATISFIABLE\nv {} 0\n", lits.join(" "));
+                    assert_eq!(graph.decode(answer.as_bytes()), Ok(Some(true)));
+                    assert!(instance.is_coloring(&graph.coloring(), k));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn decodes_every_answer_format() {
+        let instance = Instance { vertices: 2, edges: vec![(0, 1)], allowed: vec![None; 2] };
+        let mut graph = instance.graph(2);
+        for answer in ["s UNSATISFIABLE\n", "UNSAT\n", "s cnf 0 4 5\n"].iter() {
+            assert_eq!(graph.decode(answer.as_bytes()), Ok(Some(false)));
+        }
+        for answer in ["s UNKNOWN\n", "s INDETERMINATE\n", "INDET\n", "s cnf -1 4 5\n"].iter() {
+            assert_eq!(graph.decode(answer.as_bytes()), Ok(None));
+        }
+        for answer in ["SAT\n-1 2 3 -4 0\n", "s cnf 1 4 5\nv -1\nv 2\nv 3\nv -4\n"].iter() {
+            assert_eq!(graph.decode(answer.as_bytes()), Ok(Some(true)));
+            assert_eq!(graph.coloring(), vec![2, 1]);
+        }
+        // Empty, truncated or comment-only answers say nothing.
+        for answer in ["", "c nothing found\n", "s SATISFIABLE\n"].iter() {
+            assert!(graph.decode(answer.as_bytes()).is_err());
+        }
+        // Both ends of the edge get color 1.
+        assert!(graph.decode("v 1 -2 3 -4 0\n".as_bytes()).is_err());
+        assert!(graph.decode("v 1 x 0\n".as_bytes()).is_err());
+        assert!(graph.decode("v -1 -2 3 0\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+
+use bignum::BigNum;
+
+use super::Graph;
+
+/// Graphs up to this many vertices, without color limits, are counted by
+/// deletion–contraction; adjacency rows must fit in a u64.
+const CONTRACTION_LIMIT: usize = 40;
+
+/// Search pays for every coloring it finds, so it is used instead when a
+/// random graph of the same size would have at most this many.
+const SEARCH_COLORINGS: f64 = 1e8;
+
+/// Evaluates the chromatic polynomial at `colors` by deletion–contraction,
+/// on graphs given as adjacency bitmasks over vertices 0..n.
+struct Contraction {
+    colors: u64,
+    cache: HashMap<Vec<u64>, BigNum>,
+    calls: usize,
+}
+
+impl Contraction {
+    fn value(&mut self, adjacency: Vec<u64>) -> BigNum {
+        self.calls += 1;
+        if let Some(value) = self.cache.get(&adjacency) {
+            return value.clone();
+        }
+        let value = self.evaluate(&adjacency);
+        self.cache.insert(adjacency, value.clone());
+        value
+    }
+
+    fn evaluate(&mut self, adjacency: &[u64]) -> BigNum {
+        let n = adjacency.len();
+        if n == 0 {
+            return BigNum::from_u64(1);
+        }
+
+        // A vertex whose neighbors form a clique has a color for each of
+        // the k - d colors they leave, whatever the rest of the graph does.
+        for (v, &row) in adjacency.iter().enumerate() {
+            if bits(row).iter().all(|&u| row & !(adjacency[u] | 1 << u) == 0) {
+                let degree = row.count_ones() as u64;
+                if degree > self.colors {
+                    return BigNum::from_u64(0);
+                }
+                let rest = self.value(remove(adjacency, v));
+                return rest.mul(&BigNum::from_u64(self.colors - degree));
+            }
+        }
+
+        // Components are colored independently.
+        let component = reach(adjacency, 0);
+        if component.count_ones() as usize != n {
+            let inside: Vec<usize> = (0..n).filter(|&v| component >> v & 1 == 1).collect();
+            let outside: Vec<usize> = (0..n).filter(|&v| component >> v & 1 == 0).collect();
+            let first = self.value(induced(adjacency, &inside));
+            return first.mul(&self.value(induced(adjacency, &outside)));
+        }
+
+        let edges = adjacency.iter().map(|row| row.count_ones() as usize).sum::<usize>() / 2;
+        let u = (0..n).min_by_key(|&v| adjacency[v].count_ones()).unwrap();
+        if 4 * edges > n * (n - 1) {
+            // Dense: P(G) = P(G + uw) + P(G / uw) for a non-neighbor w.
+            let w = (0..n).find(|&w| w != u && adjacency[u] >> w & 1 == 0).unwrap();
+            let mut added = adjacency.to_vec();
+            added[u] |= 1 << w;
+            added[w] |= 1 << u;
+            let with = self.value(added);
+            with.add(&self.value(contract(adjacency, u, w)))
+        } else {
+            // Sparse: P(G) = P(G - uw) - P(G / uw) for a neighbor w.
+            let w = bits(adjacency[u])[0];
+            let mut deleted = adjacency.to_vec();
+            deleted[u] &= !(1 << w);
+            deleted[w] &= !(1 << u);
+            let without = self.value(deleted);
+            without.sub(&self.value(contract(adjacency, u, w)))
+        }
+    }
+}
+
+fn bits(row: u64) -> Vec<usize> {
+    (0..64).filter(|&v| row >> v & 1 == 1).collect()
+}
+
+/// Vertices connected to `start`, as a bitmask.
+fn reach(adjacency: &[u64], start: usize) -> u64 {
+    let mut seen = 1u64 << start;
+    let mut frontier = seen;
+    while frontier != 0 {
+        let v = frontier.trailing_zeros() as usize;
+        frontier &= frontier - 1;
+        let fresh = adjacency[v] & !seen;
+        seen |= fresh;
+        frontier |= fresh;
+    }
+    seen
+}
+
+/// The subgraph on `keep`, renumbered in order.
+fn induced(adjacency: &[u64], keep: &[usize]) -> Vec<u64> {
+    keep.iter()
+        .map(|&v| {
+            keep.iter()
+                .enumerate()
+                .filter(|&(_, &u)| adjacency[v] >> u & 1 == 1)
+                .fold(0, |row, (i, _)| row | 1 << i)
+        })
+        .collect()
+}
+
+fn remove(adjacency: &[u64], v: usize) -> Vec<u64> {
+    let keep: Vec<usize> = (0..adjacency.len()).filter(|&u| u != v).collect();
+    induced(adjacency, &keep)
+}
+
+/// Merges `w` into `u`.
+fn contract(adjacency: &[u64], u: usize, w: usize) -> Vec<u64> {
+    let mut merged = adjacency.to_vec();
+    let row = (adjacency[u] | adjacency[w]) & !(1 << u | 1 << w);
+    merged[u] = row;
+    for x in bits(row) {
+        merged[x] |= 1 << u;
+    }
+    remove(&merged, w)
+}
+
+impl Graph {
+    /// Calls `on_coloring` with every proper coloring using the current
+    /// number of colors and returns how many there were. Up to permutation,
+    /// only the coloring whose colors first appear in the order 1, 2, ...
+    /// is reported from each class.
+    pub fn enumerate<F: FnMut(&[usize])>(&mut self, up_to_permutation: bool, mut on_coloring: F) -> u64 {
+        let used = if self.interchangeable(up_to_permutation) { 0 } else { self.colors };
+        self.enumerate_from(0, used, &mut on_coloring)
+    }
+
+    fn enumerate_from<F: FnMut(&[usize])>(&mut self, index: usize, used: usize, on_coloring: &mut F) -> u64 {
+        if index >= self.vertices {
+            let colors: Vec<usize> = self.nodes.iter().map(|node| node.color).collect();
+            on_coloring(&colors);
+            return 1;
+        }
+
+        let mut found = 0;
+        for color in 1..self.colors.min(used + 1) + 1 {
+            if self.nodes[index].options[color - 1] == 0 {
+                continue;
+            }
+            self.explored += 1;
+            self.nodes[index].color = color;
+            if self.valid(index) {
+                found += self.enumerate_from(index + 1, used.max(color), on_coloring);
+            }
+        }
+        self.nodes[index].color = 0;
+        found
+    }
+
+    /// Counts the proper colorings with the current number of colors, which
+    /// is the chromatic polynomial at that number. Small graphs without
+    /// color limits and with many expected colorings use deletion–contraction
+    /// with a cache of the subgraphs met; others are counted by search.
+    pub fn count_colorings(&mut self, up_to_permutation: bool) -> BigNum {
+        // Each edge keeps a random coloring proper with chance 1 - 1/k.
+        let k = self.colors as f64;
+        let edges = self.nodes.iter().map(|node| node.neighbors.len()).sum::<usize>() / 2;
+        let expected = self.vertices as f64 * k.ln() + edges as f64 * (1.0 - 1.0 / k).ln();
+        if up_to_permutation || self.restricted() || self.vertices > CONTRACTION_LIMIT
+            || expected.is_nan() || expected <= SEARCH_COLORINGS.ln() {
+            return BigNum::from_u64(self.enumerate(up_to_permutation, |_| {}));
+        }
+
+        let mut adjacency = vec![0u64; self.vertices];
+        for node in self.nodes.iter() {
+            for &u in node.neighbors.iter() {
+                if u == node.index {
+                    return BigNum::from_u64(0);
+                }
+                adjacency[node.index] |= 1 << u;
+            }
+        }
+        let mut contraction = Contraction {
+            colors: self.colors as u64,
+            cache: HashMap::new(),
+            calls: 0,
+        };
+        let count = contraction.value(adjacency);
+        self.explored += contraction.calls;
+        count
+    }
+
+    /// Whether colorings are to be counted up to permutation, which only
+    /// makes sense while no vertex is limited.
+    fn interchangeable(&self, up_to_permutation: bool) -> bool {
+        if up_to_permutation && self.restricted() {
+            println!("c color limits make every color distinct; counting all colorings");
+        }
+        up_to_permutation && !self.restricted()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rng::Rng;
+    use state::testing::Instance;
+
+    fn adjacency(instance: &Instance) -> Vec<u64> {
+        let mut adjacency = vec![0u64; instance.vertices];
+        for &(u, v) in instance.edges.iter() {
+            adjacency[u] |= 1 << v;
+            adjacency[v] |= 1 << u;
+        }
+        adjacency
+    }
+
+    fn contraction(instance: &Instance, k: u64) -> BigNum {
+        Contraction { colors: k, cache: HashMap::new(), calls: 0 }.value(adjacency(instance))
+    }
+
+    fn cycle(n: usize) -> Instance {
+        Instance { vertices: n, edges: (0..n).map(|v| (v, (v + 1) % n)).collect(), allowed: vec![None; n] }
+    }
+
+    #[test]
+    fn contraction_matches_brute_force() {
+        let mut rng = Rng::new(1);
+        for _ in 0..300 {
+            let instance = Instance::random(&mut rng, 8, false, false);
+            let k = rng.below(5);
+            assert_eq!(contraction(&instance, k as u64), BigNum::from_u64(instance.colorings(k)));
+        }
+    }
+
+    #[test]
+    fn known_chromatic_polynomials() {
+        // (k - 1)^n + (-1)^n (k - 1) for cycles, including one too large to
+        // count by search, and k (k - 1) ... (k - n + 1) for cliques.
+        for &(n, k) in [(3, 3), (4, 2), (5, 3), (30, 3)].iter() {
+            let expected = (k - 1u64).pow(n as u32) as i64 + if n % 2 == 0 { 1 } else { -1 } * (k as i64 - 1);
+            assert_eq!(contraction(&cycle(n), k).to_string(), expected.to_string());
+        }
+        for n in 1..7 {
+            let edges = (0..n).flat_map(|u| (u + 1..n).map(move |v| (u, v))).collect();
+            let clique = Instance { vertices: n, edges, allowed: vec![None; n] };
+            let expected: u64 = (0..n as u64).map(|i| 5u64.saturating_sub(i)).product();
+            assert_eq!(contraction(&clique, 5), BigNum::from_u64(expected));
+        }
+        let petersen: Vec<(usize, usize)> = (0..5).flat_map(|i| vec![(i, (i + 1) % 5), (i, i + 5), (i + 5, (i + 2) % 5 + 5)]).collect();
+        let petersen = Instance { vertices: 10, edges: petersen, allowed: vec![None; 10] };
+        assert_eq!(petersen.graph(3).count_colorings(false), BigNum::from_u64(120));
+    }
+
+    #[test]
+    fn counts_match_brute_force_with_lists_and_loops() {
+        let mut rng = Rng::new(2);
+        for _ in 0..300 {
+            let instance = Instance::random(&mut rng, 8, true, true);
+            let k = rng.below(5);
+            let mut graph = instance.graph(k);
+            assert_eq!(graph.count_colorings(false), BigNum::from_u64(instance.colorings(k)));
+        }
+    }
+
+    #[test]
+    fn permutations_leave_one_coloring_per_class() {
+        let mut rng = Rng::new(3);
+        for _ in 0..200 {
+            let instance = Instance::random(&mut rng, 7, false, false);
+            let k = rng.below(5);
+            // A coloring is its class's representative when its colors
+            // first appear in the order 1, 2, ...
+            let canonical = |colors: &[usize]| {
+                let mut next = 1;
+                colors.iter().all(|&color| {
+                    if color == next {
+                        next += 1;
+                    }
+                    color < next
+                })
+            };
+            let mut all = Vec::new();
+            instance.graph(k).enumerate(false, |colors| all.push(colors.to_vec()));
+            let mut classes = Vec::new();
+            let count = instance.graph(k).enumerate(true, |colors| classes.push(colors.to_vec()));
+            assert_eq!(count as usize, classes.len());
+            all.retain(|colors| canonical(colors));
+            classes.sort();
+            assert_eq!(classes, all);
+        }
+    }
+}
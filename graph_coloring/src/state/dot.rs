@@ -0,0 +1,59 @@
+use std::io;
+use std::io::Write;
+
+use super::Graph;
+
+impl Graph {
+    /// Writes the graph in Graphviz DOT with every vertex filled by its
+    /// color; uncolored vertices stay white and edges whose ends share a
+    /// color are drawn red.
+    pub fn write_dot<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "graph coloring {{")?;
+        writeln!(out, "  node [style=filled];")?;
+        for node in self.nodes.iter() {
+            if node.color == 0 {
+                writeln!(out, "  {} [fillcolor=white];", node.index + 1)?;
+            } else {
+                // Golden ratio steps keep any number of hues apart.
+                let hue = ((node.color - 1) as f64 * 0.618_034).fract();
+                writeln!(out, "  {} [fillcolor=\"{:.3} 0.450 1.000\", tooltip=\"color {}\"];",
+                         node.index + 1, hue, node.color)?;
+            }
+        }
+        for node in self.nodes.iter() {
+            // A self-loop is listed twice among the neighbors but drawn once.
+            let looped = node.has_loop().then_some(node.index);
+            for u in node.neighbors.iter().cloned().filter(|&u| u > node.index).chain(looped) {
+                if node.color != 0 && node.color == self.nodes[u].color {
+                    writeln!(out, "  {} -- {} [color=red, penwidth=2];", node.index + 1, u + 1)?;
+                } else {
+                    writeln!(out, "  {} -- {};", node.index + 1, u + 1)?;
+                }
+            }
+        }
+        writeln!(out, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn draws_every_edge_once_and_conflicts_red() {
+        let mut graph = ::Graph::new();
+        graph.verts(3).edges(3);
+        graph.connect(1, 2);
+        graph.connect(2, 3);
+        graph.connect(3, 3);
+        graph.read_coloring("s col 2\nl 1 1\nl 2 1\nl 3 2\n".as_bytes()).unwrap();
+        let mut out = Vec::new();
+        graph.write_dot(&mut out).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+        let edges: Vec<&str> = dot.lines().filter(|line| line.contains("--")).collect();
+        assert_eq!(edges, [
+            "  1 -- 2 [color=red, penwidth=2];",
+            "  2 -- 3;",
+            "  3 -- 3 [color=red, penwidth=2];",
+        ]);
+        assert!(dot.starts_with("graph coloring {") && dot.ends_with("}\n"));
+    }
+}
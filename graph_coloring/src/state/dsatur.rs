@@ -0,0 +1,178 @@
+use std::cmp;
+
+use super::{Graph, Node};
+
+/// Colors around every vertex, kept up to date so the most saturated
+/// uncolored vertex can be found without rescanning its neighbors.
+struct Saturation {
+    color: Vec<usize>,
+    /// `counts[v][c]` is the number of neighbors of `v` with color `c`.
+    counts: Vec<Vec<usize>>,
+    saturation: Vec<usize>,
+    free_degree: Vec<usize>,
+}
+
+impl Saturation {
+    fn new(nodes: &[Node]) -> Saturation {
+        let mut sat = Saturation {
+            color: vec![0; nodes.len()],
+            counts: vec![Vec::new(); nodes.len()],
+            saturation: vec![0; nodes.len()],
+            free_degree: nodes.iter().map(|node| node.neighbors.len()).collect(),
+        };
+        for node in nodes.iter() {
+            if node.color != 0 {
+                sat.assign(nodes, node.index, node.color);
+            }
+        }
+        sat
+    }
+
+    /// True if some neighbor of `v` already has `color`.
+    fn blocked(&self, v: usize, color: usize) -> bool {
+        self.counts[v].get(color).is_some_and(|&count| count > 0)
+    }
+
+    fn assign(&mut self, nodes: &[Node], v: usize, color: usize) {
+        self.color[v] = color;
+        for &u in nodes[v].neighbors.iter() {
+            let counts = &mut self.counts[u];
+            if counts.len() <= color {
+                counts.resize(color + 1, 0);
+            }
+            if counts[color] == 0 {
+                self.saturation[u] += 1;
+            }
+            counts[color] += 1;
+            self.free_degree[u] -= 1;
+        }
+    }
+
+    fn unassign(&mut self, nodes: &[Node], v: usize) {
+        let color = self.color[v];
+        self.color[v] = 0;
+        for &u in nodes[v].neighbors.iter() {
+            self.counts[u][color] -= 1;
+            if self.counts[u][color] == 0 {
+                self.saturation[u] -= 1;
+            }
+            self.free_degree[u] += 1;
+        }
+    }
+
+    /// The uncolored vertex seeing the most distinct colors, ties broken by
+    /// the number of uncolored neighbors.
+    fn select(&self) -> Option<usize> {
+        (0..self.color.len())
+            .filter(|&v| self.color[v] == 0)
+            .max_by_key(|&v| (self.saturation[v], self.free_degree[v], cmp::Reverse(v)))
+    }
+}
+
+impl Graph {
+    /// Greedy DSatur: repeatedly gives the most saturated vertex the
+    /// smallest allowed color none of its neighbors has. Ignores the color
+    /// limit and returns the color of every vertex, so the largest one is an
+    /// upper bound on the chromatic number. Returns None if some vertex runs
+    /// out of allowed colors or has a self-loop.
+    pub fn dsatur_greedy(&self) -> Option<Vec<usize>> {
+        let mut sat = Saturation::new(&self.nodes);
+        while let Some(v) = sat.select() {
+            if self.nodes[v].has_loop() {
+                return None;
+            }
+            let color = match self.nodes[v].allowed {
+                Some(ref allowed) => allowed.iter().cloned().find(|&c| !sat.blocked(v, c))?,
+                None => (1..).find(|&c| !sat.blocked(v, c)).unwrap(),
+            };
+            sat.assign(&self.nodes, v, color);
+        }
+        Some(sat.color)
+    }
+
+    /// Exact DSatur. The greedy pass settles easy instances outright; if it
+    /// needs too many colors, branch and bound over the most saturated
+    /// vertex, trying each color already in use and at most one new one.
+    pub fn color_dsatur(&mut self) -> bool {
+        if let Some(greedy) = self.dsatur_greedy() {
+            if greedy.iter().all(|&color| color <= self.colors) {
+                for (node, color) in self.nodes.iter_mut().zip(greedy) {
+                    node.color = color;
+                }
+                return true;
+            }
+        }
+
+        let mut sat = Saturation::new(&self.nodes);
+        // Limited vertices make colors distinguishable.
+        let used = if self.restricted() {
+            self.colors
+        } else {
+            self.nodes.iter().map(|node| node.color).max().unwrap_or(0)
+        };
+        self.dsatur_search(&mut sat, used)
+    }
+
+    fn dsatur_search(&mut self, sat: &mut Saturation, used: usize) -> bool {
+        let v = match sat.select() {
+            Some(v) => v,
+            None => return true,
+        };
+        if self.nodes[v].has_loop() {
+            return false;
+        }
+
+        for color in 1..cmp::min(self.colors, used + 1) + 1 {
+            if sat.blocked(v, color) || self.nodes[v].options[color - 1] == 0 {
+                continue;
+            }
+            self.explored += 1;
+            sat.assign(&self.nodes, v, color);
+            self.nodes[v].color = color;
+            if self.dsatur_search(sat, cmp::max(used, color)) {
+                return true;
+            }
+            sat.unassign(&self.nodes, v);
+            self.nodes[v].color = 0;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rng::Rng;
+    use state::testing::{check_algorithm, Instance};
+    use Algorithm;
+
+    #[test]
+    fn exact_dsatur_matches_brute_force() {
+        check_algorithm(Algorithm::DSatur, 1, false);
+    }
+
+    #[test]
+    fn greedy_colorings_are_proper_upper_bounds() {
+        let mut rng = Rng::new(2);
+        for _ in 0..300 {
+            let instance = Instance::random(&mut rng, 10, true, true);
+            let graph = instance.graph(6);
+            match graph.dsatur_greedy() {
+                Some(colors) => {
+                    let most = colors.iter().cloned().max().unwrap_or(0);
+                    assert!(instance.is_coloring(&colors, most));
+                },
+                // Lists or a self-loop left some vertex without a color.
+                None => assert!(instance.edges.iter().any(|&(u, v)| u == v) || instance.allowed.iter().any(|a| a.is_some())),
+            }
+        }
+    }
+
+    #[test]
+    fn greedy_is_exact_on_bipartite_graphs() {
+        for n in (4..20).step_by(2) {
+            let instance = Instance { vertices: n, edges: (0..n).map(|v| (v, (v + 1) % n)).collect(), allowed: vec![None; n] };
+            let colors = instance.graph(3).dsatur_greedy().unwrap();
+            assert!(instance.is_coloring(&colors, 2));
+        }
+    }
+}
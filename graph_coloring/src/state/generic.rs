@@ -0,0 +1,103 @@
+use csp::{Csp, NotEqual, Strategy};
+
+use super::Graph;
+
+impl Graph {
+    /// The coloring problem as a generic CSP: one variable per vertex whose
+    /// domain is its remaining options, and neighbors differ. A vertex with
+    /// a self-loop gets an empty domain.
+    pub fn to_csp(&self) -> Csp {
+        let mut csp = Csp::new();
+        for node in self.nodes.iter() {
+            let domain: Vec<usize> = node.options.iter()
+                .cloned()
+                .filter(|&color| color != 0 && !node.has_loop())
+                .collect();
+            csp.variable(&domain);
+        }
+        for node in self.nodes.iter() {
+            for &u in node.neighbors.iter().filter(|&&u| u > node.index) {
+                csp.constrain(NotEqual::new(node.index, u));
+            }
+        }
+        csp
+    }
+
+    /// Solves the coloring through the generic CSP engine, propagating
+    /// every constraint.
+    pub fn color_csp(&mut self) -> bool {
+        let mut csp = self.to_csp();
+        self.run_csp(&mut csp)
+    }
+
+    /// The dfs, fc and mcv searches. Colors are interchangeable unless some
+    /// vertex is limited, so a vertex tries at most one color no other vertex
+    /// has yet. With symmetry breaking on, the vertices of a greedy clique
+    /// are fixed to 1, 2, ... first, since any coloring can be renamed to
+    /// agree with it.
+    pub fn color_search(&mut self, strategy: Strategy) -> bool {
+        let mut csp = self.to_csp();
+        csp.strategy(strategy);
+        if !self.restricted() {
+            let mut used = 0;
+            if self.symmetry {
+                let clique = self.greedy_clique();
+                if clique.len() > self.colors {
+                    return false;
+                }
+                for (i, &v) in clique.iter().enumerate() {
+                    csp.fix(v, i + 1);
+                }
+                used = clique.len();
+            }
+            csp.interchangeable(used);
+        }
+        self.run_csp(&mut csp)
+    }
+
+    /// Solves `csp`, built by `to_csp`, and copies the colors back.
+    fn run_csp(&mut self, csp: &mut Csp) -> bool {
+        let solution = csp.solve();
+        self.explored += csp.explored;
+        match solution {
+            Some(colors) => {
+                for (node, color) in self.nodes.iter_mut().zip(colors) {
+                    node.color = color;
+                }
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rng::Rng;
+    use state::testing::Instance;
+
+    #[test]
+    fn csp_coloring_matches_brute_force() {
+        let mut rng = Rng::new(6);
+        for _ in 0..300 {
+            let instance = Instance::random(&mut rng, 8, true, true);
+            let k = rng.below(5);
+            let mut graph = instance.graph(k);
+            if graph.color_csp() {
+                assert!(instance.is_coloring(&graph.coloring(), k));
+            } else {
+                assert!(!instance.colorable(k));
+            }
+        }
+    }
+
+    #[test]
+    fn self_loop_has_no_color() {
+        let mut graph = ::Graph::new();
+        graph.verts(2).edges(1);
+        graph.connect(2, 2);
+        graph.colors(2);
+        assert_eq!(graph.to_csp().solve(), None);
+        assert!(!graph.color_csp());
+    }
+}
@@ -0,0 +1,255 @@
+use rng::Rng;
+
+use super::{Graph, Node};
+
+/// Chance that min-conflicts recolors at random instead of greedily.
+const WALK_PROBABILITY: f64 = 0.1;
+
+/// A complete, possibly improper coloring together with how many
+/// neighbors of each vertex have each color. A self-loop would make a vertex
+/// count itself, so graphs with one are never searched.
+struct Conflicts {
+    color: Vec<usize>,
+    /// `gamma[v][c]` is the number of neighbors of `v` with color `c`.
+    gamma: Vec<Vec<usize>>,
+    /// Vertices sharing their color with a neighbor, in no order.
+    conflicting: Vec<usize>,
+    position: Vec<Option<usize>>,
+    /// Number of edges whose ends have the same color.
+    total: usize,
+}
+
+/// The colors `node` may take.
+fn allowed(node: &Node) -> Vec<usize> {
+    node.options.iter().cloned().filter(|&color| color != 0).collect()
+}
+
+impl Conflicts {
+    fn random(nodes: &[Node], colors: usize, rng: &mut Rng) -> Conflicts {
+        let color: Vec<usize> = nodes.iter()
+            .map(|node| {
+                let allowed = allowed(node);
+                allowed[rng.below(allowed.len())]
+            })
+            .collect();
+        let mut gamma = vec![vec![0; colors + 1]; nodes.len()];
+        for node in nodes.iter() {
+            for &u in node.neighbors.iter() {
+                gamma[u][color[node.index]] += 1;
+            }
+        }
+        let mut conflicts = Conflicts {
+            total: (0..nodes.len()).map(|v| gamma[v][color[v]]).sum::<usize>() / 2,
+            color,
+            gamma,
+            conflicting: Vec::new(),
+            position: vec![None; nodes.len()],
+        };
+        for v in 0..nodes.len() {
+            conflicts.update(v);
+        }
+        conflicts
+    }
+
+    /// Brings the membership of `v` in `conflicting` up to date.
+    fn update(&mut self, v: usize) {
+        let conflicting = self.gamma[v][self.color[v]] > 0;
+        match (conflicting, self.position[v]) {
+            (true, None) => {
+                self.position[v] = Some(self.conflicting.len());
+                self.conflicting.push(v);
+            },
+            (false, Some(i)) => {
+                let last = self.conflicting.pop().unwrap();
+                if last != v {
+                    self.conflicting[i] = last;
+                    self.position[last] = Some(i);
+                }
+                self.position[v] = None;
+            },
+            _ => {},
+        }
+    }
+
+    /// Change in conflicting edges if `v` took `color`.
+    fn delta(&self, v: usize, color: usize) -> isize {
+        self.gamma[v][color] as isize - self.gamma[v][self.color[v]] as isize
+    }
+
+    fn recolor(&mut self, nodes: &[Node], v: usize, color: usize) {
+        let old = self.color[v];
+        self.total = (self.total as isize + self.delta(v, color)) as usize;
+        self.color[v] = color;
+        for &u in nodes[v].neighbors.iter() {
+            self.gamma[u][old] -= 1;
+            self.gamma[u][color] += 1;
+            self.update(u);
+        }
+        self.update(v);
+    }
+}
+
+impl Graph {
+    /// Tabucol: always makes the best recoloring of a conflicting vertex,
+    /// forbidding a vertex to return to a color it just left for a while
+    /// unless that would beat the best coloring seen.
+    pub fn color_tabucol(&mut self) -> bool {
+        if self.nodes.iter().any(|node| allowed(node).is_empty() || node.has_loop()) {
+            return false;
+        }
+        let mut rng = Rng::new(self.seed);
+        let mut state = Conflicts::random(&self.nodes, self.colors, &mut rng);
+        let mut tabu = vec![vec![0; self.colors + 1]; self.vertices];
+        let mut best = state.total;
+        println!("c best {} conflicts after 0 iterations", best);
+
+        let mut iteration = 0;
+        while state.total > 0 && iteration < self.iterations {
+            iteration += 1;
+            // Best non-tabu move, ties broken uniformly at random.
+            let mut chosen = None;
+            let mut chosen_delta = isize::MAX;
+            let mut ties = 0;
+            for &v in state.conflicting.iter() {
+                for (color, &until) in tabu[v].iter().enumerate().skip(1) {
+                    if color == state.color[v] || self.nodes[v].options[color - 1] == 0 {
+                        continue;
+                    }
+                    let delta = state.delta(v, color);
+                    let aspiration = (state.total as isize + delta) < best as isize;
+                    if until > iteration && !aspiration || delta > chosen_delta {
+                        continue;
+                    }
+                    if delta < chosen_delta {
+                        chosen_delta = delta;
+                        ties = 0;
+                    }
+                    ties += 1;
+                    if rng.below(ties) == 0 {
+                        chosen = Some((v, color));
+                    }
+                }
+            }
+
+            let (v, color) = match chosen {
+                Some(chosen) => chosen,
+                None => {
+                    // Everything is tabu; move a conflicting vertex at random.
+                    let v = state.conflicting[rng.below(state.conflicting.len())];
+                    let others: Vec<usize> = allowed(&self.nodes[v]).into_iter()
+                        .filter(|&color| color != state.color[v])
+                        .collect();
+                    if others.is_empty() {
+                        continue;
+                    }
+                    (v, others[rng.below(others.len())])
+                },
+            };
+            let tenure = rng.below(10) + state.conflicting.len() * 3 / 5;
+            tabu[v][state.color[v]] = iteration + tenure;
+            state.recolor(&self.nodes, v, color);
+            self.explored += 1;
+
+            if state.total < best {
+                best = state.total;
+                println!("c best {} conflicts after {} iterations", best, iteration);
+            }
+        }
+        self.apply(&state)
+    }
+
+    /// Min-conflicts with random walk: recolors a random conflicting vertex,
+    /// usually with the color it clashes least with, sometimes at random.
+    pub fn color_min_conflicts(&mut self) -> bool {
+        if self.nodes.iter().any(|node| allowed(node).is_empty() || node.has_loop()) {
+            return false;
+        }
+        let mut rng = Rng::new(self.seed);
+        let mut state = Conflicts::random(&self.nodes, self.colors, &mut rng);
+        let mut best = state.total;
+        println!("c best {} conflicts after 0 iterations", best);
+
+        let mut iteration = 0;
+        while state.total > 0 && iteration < self.iterations {
+            iteration += 1;
+            let v = state.conflicting[rng.below(state.conflicting.len())];
+            let allowed = allowed(&self.nodes[v]);
+            let color = if rng.chance(WALK_PROBABILITY) {
+                allowed[rng.below(allowed.len())]
+            } else {
+                let least = allowed.iter().map(|&c| state.gamma[v][c]).min().unwrap();
+                let options: Vec<usize> = allowed.into_iter()
+                    .filter(|&c| state.gamma[v][c] == least)
+                    .collect();
+                options[rng.below(options.len())]
+            };
+            if color != state.color[v] {
+                state.recolor(&self.nodes, v, color);
+            }
+            self.explored += 1;
+
+            if state.total < best {
+                best = state.total;
+                println!("c best {} conflicts after {} iterations", best, iteration);
+            }
+        }
+        self.apply(&state)
+    }
+
+    /// Takes the coloring over if it is proper.
+    fn apply(&mut self, state: &Conflicts) -> bool {
+        if state.total > 0 {
+            return false;
+        }
+        for (node, &color) in self.nodes.iter_mut().zip(state.color.iter()) {
+            node.color = color;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rng::Rng;
+    use state::testing::{check_algorithm, Instance};
+    use Algorithm;
+
+    #[test]
+    fn colorings_found_are_proper() {
+        check_algorithm(Algorithm::Tabucol, 5, false);
+        check_algorithm(Algorithm::MinConflicts, 6, false);
+    }
+
+    #[test]
+    fn colors_graphs_with_room_to_spare() {
+        // One color more than the largest degree always suffices.
+        let mut rng = Rng::new(7);
+        for _ in 0..50 {
+            let instance = Instance::random(&mut rng, 30, false, false);
+            let mut degree = vec![0; instance.vertices];
+            for &(u, v) in instance.edges.iter() {
+                degree[u] += 1;
+                degree[v] += 1;
+            }
+            let k = degree.iter().cloned().max().unwrap() + 1;
+            for &alg in [Algorithm::Tabucol, Algorithm::MinConflicts].iter() {
+                let mut graph = instance.graph(k);
+                graph.iterations(100_000);
+                assert!(graph.solve(alg));
+                assert!(instance.is_coloring(&graph.coloring(), k));
+            }
+        }
+    }
+
+    #[test]
+    fn seeds_reproduce_the_search() {
+        let instance = Instance::random(&mut Rng::new(8), 30, false, false);
+        let run = |seed| {
+            let mut graph = instance.graph(4);
+            graph.seed(seed).iterations(500);
+            let found = graph.solve(Algorithm::Tabucol);
+            (found, graph.coloring(), graph.explored)
+        };
+        assert_eq!(run(3), run(3));
+    }
+}
@@ -0,0 +1,116 @@
+use super::Graph;
+
+impl Graph {
+    fn domain_size(&self, v: usize) -> usize {
+        self.nodes[v].options.iter().filter(|&&color| color != 0).count()
+    }
+
+    /// Removes `color` from the domain of `v`, remembering it on the trail.
+    fn prune(&mut self, v: usize, color: usize, trail: &mut Vec<(usize, usize)>) {
+        self.nodes[v].options[color - 1] = 0;
+        trail.push((v, color));
+    }
+
+    fn restore(&mut self, trail: &mut Vec<(usize, usize)>, mark: usize) {
+        while trail.len() > mark {
+            let (v, color) = trail.pop().unwrap();
+            self.nodes[v].options[color - 1] = color;
+        }
+    }
+
+    /// AC-3 for "neighbors differ". An arc into a vertex can only lose
+    /// support once the other end is down to a single color, so only those
+    /// vertices are queued. Returns false on a domain wipeout.
+    fn arc_consistency(&mut self, mut queue: Vec<usize>, trail: &mut Vec<(usize, usize)>) -> bool {
+        while let Some(v) = queue.pop() {
+            let color = match self.nodes[v].options.iter().find(|&&color| color != 0) {
+                Some(&color) => color,
+                None => return false,
+            };
+            let neighbors = self.nodes[v].neighbors.clone();
+            for u in neighbors {
+                if self.nodes[u].options[color - 1] == 0 {
+                    continue;
+                }
+                self.prune(u, color, trail);
+                match self.domain_size(u) {
+                    0 => return false,
+                    1 => queue.push(u),
+                    _ => {},
+                }
+            }
+        }
+        true
+    }
+
+    /// Maintaining arc consistency: after every assignment the domains are
+    /// made arc consistent again, so a wipeout anywhere backtracks at once.
+    /// Branches on the vertex with the fewest colors left.
+    pub fn color_mac(&mut self) -> bool {
+        let mut trail = Vec::new();
+        let singletons: Vec<usize> = (0..self.vertices)
+            .filter(|&v| self.domain_size(v) <= 1)
+            .collect();
+        if !self.arc_consistency(singletons, &mut trail) {
+            return false;
+        }
+        self.mac_search(&mut trail)
+    }
+
+    fn mac_search(&mut self, trail: &mut Vec<(usize, usize)>) -> bool {
+        let v = match (0..self.vertices)
+            .filter(|&v| self.nodes[v].color == 0)
+            .min_by_key(|&v| (self.domain_size(v), usize::MAX - self.nodes[v].neighbors.len())) {
+            Some(v) => v,
+            None => return true,
+        };
+
+        let options = self.nodes[v].options.clone();
+        for &color in options.iter() {
+            if color == 0 {
+                continue;
+            }
+            self.explored += 1;
+            let mark = trail.len();
+            self.nodes[v].color = color;
+            for &other in options.iter() {
+                if other != 0 && other != color {
+                    self.prune(v, other, trail);
+                }
+            }
+            if self.arc_consistency(vec![v], trail) && self.mac_search(trail) {
+                return true;
+            }
+            self.restore(trail, mark);
+            self.nodes[v].color = 0;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use state::testing::{check_algorithm, Instance};
+    use Algorithm;
+
+    #[test]
+    fn mac_matches_brute_force() {
+        check_algorithm(Algorithm::ArcConsistency, 3, false);
+    }
+
+    #[test]
+    fn propagation_alone_settles_a_forced_path() {
+        // One end is fixed, so every color along a 2-colored path follows
+        // from arc consistency: each vertex is colored once and no branch
+        // ever fails.
+        let instance = Instance {
+            vertices: 12,
+            edges: (0..11).map(|v| (v, v + 1)).collect(),
+            allowed: (0..12).map(|v| if v == 0 { Some(vec![2]) } else { None }).collect(),
+        };
+        let mut graph = instance.graph(2);
+        assert!(graph.color_mac());
+        assert!(instance.is_coloring(&graph.coloring(), 2));
+        assert_eq!(graph.explored, 12);
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use super::{Algorithm, Graph};
+
+/// How much the last search shrank the graph before coloring it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reduction {
+    pub vertices: usize,
+    pub remaining: usize,
+    pub components: usize,
+}
+
+impl Display for Reduction {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "reduced {} vertices to {} in {} components", self.vertices, self.remaining, self.components)
+    }
+}
+
+/// A vertex taken out of the graph before the search, in the order of
+/// removal. Putting them back in reverse order always works.
+enum Removed {
+    /// It had fewer neighbors left than colors it may take, so one of them
+    /// stays free whatever its neighbors get.
+    Peeled(usize),
+    /// Its remaining neighbors are all neighbors of the other, non-adjacent
+    /// vertex, whose color it can share.
+    Dominated(usize, usize),
+}
+
+impl Graph {
+    fn colors_allowed(&self, v: usize) -> usize {
+        self.nodes[v].options.iter().filter(|&&color| color != 0).count()
+    }
+
+    /// Colors the graph with `alg` after shrinking it: vertices with fewer
+    /// neighbors than colors are peeled off and dominated vertices merged
+    /// into the vertex dominating them, repeatedly, and what is left is
+    /// split into connected components that are searched one at a time.
+    /// The removed vertices are colored last. Records what was removed in
+    /// `reduction`.
+    pub fn color_reduced(&mut self, alg: Algorithm) -> bool {
+        self.reduction = None;
+        let mut sorted: Vec<Vec<usize>> = self.nodes.iter().map(|node| node.neighbors.clone()).collect();
+        for neighbors in sorted.iter_mut() {
+            neighbors.sort();
+            neighbors.dedup();
+        }
+        let mut present = vec![true; self.vertices];
+        let mut degree: Vec<usize> = sorted.iter().map(|neighbors| neighbors.len()).collect();
+        let mut removed = Vec::new();
+
+        loop {
+            let before = removed.len();
+            let mut queue: Vec<usize> = (0..self.vertices).collect();
+            while let Some(v) = queue.pop() {
+                if !present[v] || degree[v] >= self.colors_allowed(v) || sorted[v].binary_search(&v).is_ok() {
+                    continue;
+                }
+                present[v] = false;
+                removed.push(Removed::Peeled(v));
+                for &u in sorted[v].iter().filter(|&&u| present[u]) {
+                    degree[u] -= 1;
+                    queue.push(u);
+                }
+            }
+
+            for u in 0..self.vertices {
+                if !present[u] {
+                    continue;
+                }
+                if let Some(v) = self.dominator(u, &sorted, &present, &degree) {
+                    present[u] = false;
+                    removed.push(Removed::Dominated(u, v));
+                    for &w in sorted[u].iter().filter(|&&w| present[w]) {
+                        degree[w] -= 1;
+                    }
+                }
+            }
+            if removed.len() == before {
+                break;
+            }
+        }
+
+        let components = components(&sorted, &present);
+        if removed.is_empty() && components.len() <= 1 {
+            return self.solve(alg);
+        }
+        self.reduction = Some(Reduction {
+            vertices: self.vertices,
+            remaining: self.vertices - removed.len(),
+            components: components.len(),
+        });
+
+        for component in components.iter() {
+            if !self.solve_component(alg, component, &sorted) {
+                return false;
+            }
+        }
+        if self.restore_removed(&removed).is_err() {
+            // Cannot happen if the removals were sound; give up on them.
+            self.reduction = None;
+            for node in self.nodes.iter_mut() {
+                node.color = 0;
+            }
+            return self.solve(alg);
+        }
+        true
+    }
+
+    /// Colors the removed vertices, last removed first. Returns the vertex
+    /// that found no color, if any.
+    fn restore_removed(&mut self, removed: &[Removed]) -> Result<(), usize> {
+        for removal in removed.iter() {
+            match *removal {
+                Removed::Peeled(v) | Removed::Dominated(v, _) => self.nodes[v].color = 0,
+            }
+        }
+        for removal in removed.iter().rev() {
+            let (v, color) = match *removal {
+                Removed::Peeled(v) => {
+                    let node = &self.nodes[v];
+                    let free = node.options.iter()
+                        .cloned()
+                        .find(|&color| color != 0 && node.neighbors.iter().all(|&u| self.nodes[u].color != color));
+                    (v, free.ok_or(v)?)
+                },
+                Removed::Dominated(u, v) => (u, self.nodes[v].color),
+            };
+            self.nodes[v].color = color;
+            if color == 0 || self.nodes[v].options[color - 1] == 0 || !self.valid(v) {
+                self.nodes[v].color = 0;
+                return Err(v);
+            }
+        }
+        Ok(())
+    }
+
+    /// A present vertex, not adjacent to `u`, adjacent to all present
+    /// neighbors of `u` and allowed no color `u` is not.
+    fn dominator(&self, u: usize, sorted: &[Vec<usize>], present: &[bool], degree: &[usize]) -> Option<usize> {
+        let live: Vec<usize> = sorted[u].iter().cloned().filter(|&w| present[w]).collect();
+        // Candidates must also neighbor the neighbor with the fewest neighbors.
+        let pivot = live.iter().cloned().min_by_key(|&w| degree[w])?;
+        let options = &self.nodes[u].options;
+        sorted[pivot].iter().cloned().find(|&v| {
+            v != u && present[v] && degree[v] >= degree[u]
+                && sorted[v].binary_search(&u).is_err()
+                && live.iter().all(|w| sorted[v].binary_search(w).is_ok())
+                && self.nodes[v].options.iter().zip(options.iter()).all(|(&a, &b)| a == 0 || b != 0)
+        })
+    }
+
+    /// Colors the vertices of `component` as a graph of their own.
+    fn solve_component(&mut self, alg: Algorithm, component: &[usize], sorted: &[Vec<usize>]) -> bool {
+        let mut position = vec![None; self.vertices];
+        for (i, &v) in component.iter().enumerate() {
+            position[v] = Some(i);
+        }
+
+        let mut sub = Graph::new();
+        sub.verts(component.len())
+            .colors(self.colors)
+            .seed(self.seed)
+            .iterations(self.iterations)
+            .symmetry(self.symmetry);
+        let mut edges = 0;
+        for (i, &v) in component.iter().enumerate() {
+            sub.nodes[i].options = self.nodes[v].options.clone();
+            sub.nodes[i].allowed = self.nodes[v].allowed.clone();
+            for &u in sorted[v].iter() {
+                if let Some(j) = position[u] {
+                    if j >= i {
+                        sub.connect(i + 1, j + 1);
+                        edges += 1;
+                    }
+                }
+            }
+        }
+        sub.edges(edges);
+
+        let solved = sub.solve(alg);
+        self.explored += sub.explored;
+        if solved {
+            for (node, &v) in sub.nodes.iter().zip(component.iter()) {
+                self.nodes[v].color = node.color;
+            }
+        }
+        solved
+    }
+}
+
+/// Connected components of the present vertices.
+fn components(sorted: &[Vec<usize>], present: &[bool]) -> Vec<Vec<usize>> {
+    let mut seen = present.iter().map(|&present| !present).collect::<Vec<bool>>();
+    let mut components = Vec::new();
+    for start in 0..present.len() {
+        if seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        let mut component = Vec::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        while let Some(v) = queue.pop_front() {
+            component.push(v);
+            for &u in sorted[v].iter() {
+                if !seen[u] {
+                    seen[u] = true;
+                    queue.push_back(u);
+                }
+            }
+        }
+        component.sort();
+        components.push(component);
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Reduction;
+    use rng::Rng;
+    use state::testing::Instance;
+    use Algorithm;
+
+    #[test]
+    fn reduced_search_matches_brute_force() {
+        let mut rng = Rng::new(8);
+        for _ in 0..500 {
+            let instance = Instance::random(&mut rng, 9, true, true);
+            let k = rng.below(5);
+            let mut graph = instance.graph(k);
+            if graph.color_reduced(Algorithm::DepthFirst) {
+                assert!(instance.is_coloring(&graph.coloring(), k));
+            } else {
+                assert!(!instance.colorable(k));
+            }
+        }
+    }
+
+    #[test]
+    fn peels_a_path_completely() {
+        let instance = Instance {
+            vertices: 5,
+            edges: vec![(0, 1), (1, 2), (2, 3), (3, 4)],
+            allowed: vec![None; 5],
+        };
+        let mut graph = instance.graph(2);
+        assert!(graph.color_reduced(Algorithm::DepthFirst));
+        assert!(instance.is_coloring(&graph.coloring(), 2));
+        assert_eq!(graph.reduction, Some(Reduction { vertices: 5, remaining: 0, components: 0 }));
+    }
+
+    /// The Petersen graph on vertices `first..first + 10`, which has three
+    /// neighbors everywhere and no dominated vertex.
+    fn petersen(first: usize) -> Vec<(usize, usize)> {
+        (0..5)
+            .flat_map(|i| vec![(i, (i + 1) % 5), (i, i + 5), (i + 5, (i + 2) % 5 + 5)])
+            .map(|(u, v)| (first + u, first + v))
+            .collect()
+    }
+
+    #[test]
+    fn splits_components_and_merges_dominated_vertices() {
+        // Vertex 20 has the neighbors of vertex 0, so one of them goes.
+        let mut edges = petersen(0);
+        edges.extend(petersen(10));
+        edges.extend(vec![(20, 1), (20, 4), (20, 5)]);
+        let instance = Instance { vertices: 21, edges, allowed: vec![None; 21] };
+        let mut graph = instance.graph(3);
+        assert!(graph.color_reduced(Algorithm::DepthFirst));
+        assert!(instance.is_coloring(&graph.coloring(), 3));
+        assert_eq!(graph.reduction, Some(Reduction { vertices: 21, remaining: 20, components: 2 }));
+        assert_eq!(graph.coloring()[0], graph.coloring()[20]);
+    }
+
+    #[test]
+    fn records_nothing_when_nothing_shrinks() {
+        let instance = Instance { vertices: 10, edges: petersen(0), allowed: vec![None; 10] };
+        let mut graph = instance.graph(3);
+        assert!(graph.color_reduced(Algorithm::DepthFirst));
+        assert_eq!(graph.reduction, None);
+    }
+}
@@ -0,0 +1,138 @@
+//! Small random graphs and brute-force answers for the unit tests.
+
+use rng::Rng;
+
+use super::{Algorithm, Graph};
+
+/// A graph as plain data, vertices numbered from 0.
+pub struct Instance {
+    pub vertices: usize,
+    pub edges: Vec<(usize, usize)>,
+    /// The color list of every vertex, if it has one.
+    pub allowed: Vec<Option<Vec<usize>>>,
+}
+
+impl Instance {
+    /// Up to `most` vertices with a random edge density. With `loops`, a
+    /// few vertices are their own neighbors; with `lists`, some vertices are
+    /// fixed or limited to colors from 1..6.
+    pub fn random(rng: &mut Rng, most: usize, loops: bool, lists: bool) -> Instance {
+        let vertices = 1 + rng.below(most);
+        let density = rng.next_f64();
+        let mut edges = Vec::new();
+        for u in 0..vertices {
+            if loops && rng.chance(0.05) {
+                edges.push((u, u));
+            }
+            for v in u + 1..vertices {
+                if rng.chance(density) {
+                    edges.push((u, v));
+                }
+            }
+        }
+        let allowed = (0..vertices)
+            .map(|_| {
+                let roll = rng.next_f64();
+                if !lists || roll > 0.45 {
+                    None
+                } else if roll < 0.2 {
+                    Some(vec![1 + rng.below(6)])
+                } else {
+                    Some((1..7).filter(|_| rng.chance(0.4)).collect())
+                }
+            })
+            .collect();
+        Instance { vertices, edges, allowed }
+    }
+
+    pub fn graph(&self, colors: usize) -> Graph {
+        let mut graph = Graph::new();
+        graph.verts(self.vertices).edges(self.edges.len());
+        for &(u, v) in self.edges.iter() {
+            graph.connect(u + 1, v + 1);
+        }
+        for (v, allowed) in self.allowed.iter().enumerate() {
+            if let Some(ref allowed) = *allowed {
+                graph.allow(v + 1, allowed);
+            }
+        }
+        graph.colors(colors);
+        graph
+    }
+
+    fn fits(&self, v: usize, color: usize) -> bool {
+        self.allowed[v].as_ref().is_none_or(|allowed| allowed.contains(&color))
+    }
+
+    /// Whether `colors` is a proper coloring within the lists and 1..k + 1.
+    pub fn is_coloring(&self, colors: &[usize], k: usize) -> bool {
+        colors.len() == self.vertices
+            && colors.iter().enumerate().all(|(v, &color)| 1 <= color && color <= k && self.fits(v, color))
+            && self.edges.iter().all(|&(u, v)| colors[u] != colors[v])
+    }
+
+    /// The number of proper colorings with `k` colors, by backtracking.
+    pub fn colorings(&self, k: usize) -> u64 {
+        self.search(k, false)
+    }
+
+    pub fn colorable(&self, k: usize) -> bool {
+        self.search(k, true) > 0
+    }
+
+    /// Counts colorings, or only looks for one if `first`.
+    fn search(&self, k: usize, first: bool) -> u64 {
+        if self.edges.iter().any(|&(u, v)| u == v) {
+            return 0;
+        }
+        let mut colors = vec![0; self.vertices];
+        self.extend(&mut colors, 0, k, first)
+    }
+
+    fn extend(&self, colors: &mut Vec<usize>, v: usize, k: usize, first: bool) -> u64 {
+        if v == self.vertices {
+            return 1;
+        }
+        let mut count = 0;
+        for color in 1..k + 1 {
+            let clash = self.edges.iter()
+                .any(|&(a, b)| a == v && b < v && colors[b] == color || b == v && a < v && colors[a] == color);
+            if self.fits(v, color) && !clash {
+                colors[v] = color;
+                count += self.extend(colors, v + 1, k, first);
+                if first && count > 0 {
+                    break;
+                }
+            }
+        }
+        colors[v] = 0;
+        count
+    }
+}
+
+/// Runs `alg` on its own, without reduction, on random graphs with
+/// self-loops and color lists, and checks every answer by backtracking.
+/// Incomplete algorithms only have to return proper colorings.
+pub fn check_algorithm(alg: Algorithm, seed: u64, symmetry: bool) {
+    let mut rng = Rng::new(seed);
+    for _ in 0..400 {
+        let instance = Instance::random(&mut rng, 9, true, true);
+        let k = rng.below(5);
+        let mut graph = instance.graph(k);
+        graph.symmetry(symmetry).seed(seed).iterations(2000);
+        let found = graph.solve(alg);
+        if found {
+            assert!(instance.is_coloring(&graph.coloring(), k));
+        }
+        if alg.is_complete() {
+            assert_eq!(found, instance.colorable(k), "{} colors", k);
+        }
+    }
+}
+
+impl Graph {
+    /// The color of every vertex.
+    pub fn coloring(&self) -> Vec<usize> {
+        self.nodes.iter().map(|node| node.color).collect()
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::io::BufRead;
+
+use super::Graph;
+
+/// Something wrong with a coloring, with vertices numbered from 1.
+pub enum Fault {
+    Conflict { u: usize, v: usize, color: usize },
+    Uncolored { v: usize },
+    OutOfRange { v: usize, color: usize },
+    NotAllowed { v: usize, color: usize },
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Fault::Conflict { u, v, color } => write!(f, "edge {} {} has color {} at both ends", u, v, color),
+            Fault::Uncolored { v } => write!(f, "vertex {} is uncolored", v),
+            Fault::OutOfRange { v, color } => write!(f, "vertex {} has color {} beyond the announced colors", v, color),
+            Fault::NotAllowed { v, color } => write!(f, "vertex {} has color {} outside its list", v, color),
+        }
+    }
+}
+
+impl Graph {
+    /// Reads a coloring in the `s col K` / `l V C` format that
+    /// `print_coloring` writes. Returns false if it contains no `s col`
+    /// line, as when the solver found no coloring.
+    pub fn read_coloring<R: BufRead>(&mut self, input: R) -> Result<bool, String> {
+        let mut found = false;
+        for (number, line) in (1..).zip(input.lines()) {
+            let line = line.map_err(|e| e.to_string())?;
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let number_at = |i: usize| words.get(i).and_then(|word| word.parse::<usize>().ok());
+            match words.first() {
+                Some(&"s") if words.get(1) == Some(&"col") => match number_at(2) {
+                    Some(colors) if words.len() == 3 => {
+                        self.colors(colors);
+                        found = true;
+                    },
+                    _ => return Err(format!("line {}: cannot read {}", number, line)),
+                },
+                Some(&"l") => match (number_at(1), number_at(2)) {
+                    (Some(v), Some(color)) if words.len() == 3 => {
+                        if v == 0 || v > self.vertices {
+                            return Err(format!("line {}: no vertex {}", number, v));
+                        }
+                        self.nodes[v - 1].color = color;
+                    },
+                    _ => return Err(format!("line {}: cannot read {}", number, line)),
+                },
+                _ => continue,
+            }
+        }
+        Ok(found)
+    }
+
+    /// Every conflicting edge, uncolored vertex, color above the current
+    /// number of colors and color a vertex is not allowed.
+    pub fn faults(&self) -> Vec<Fault> {
+        let mut faults = Vec::new();
+        for node in self.nodes.iter() {
+            if node.color == 0 {
+                faults.push(Fault::Uncolored { v: node.index + 1 });
+            } else if node.color > self.colors {
+                faults.push(Fault::OutOfRange { v: node.index + 1, color: node.color });
+            } else if node.allowed.as_ref().is_some_and(|allowed| !allowed.contains(&node.color)) {
+                faults.push(Fault::NotAllowed { v: node.index + 1, color: node.color });
+            }
+        }
+        for node in self.nodes.iter() {
+            // A self-loop is listed twice among the neighbors but reported once.
+            let looped = node.has_loop().then_some(node.index);
+            for u in node.neighbors.iter().cloned().filter(|&u| u > node.index).chain(looped) {
+                if node.color != 0 && node.color == self.nodes[u].color {
+                    faults.push(Fault::Conflict { u: node.index + 1, v: u + 1, color: node.color });
+                }
+            }
+        }
+        faults
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fault;
+    use rng::Rng;
+    use state::testing::Instance;
+
+    #[test]
+    fn faults_match_brute_force() {
+        let mut rng = Rng::new(7);
+        for _ in 0..500 {
+            let instance = Instance::random(&mut rng, 7, true, true);
+            let k = 1 + rng.below(4);
+            let mut graph = instance.graph(k);
+            let mut text = format!("s col {}\n", k);
+            let colors: Vec<usize> = (0..instance.vertices).map(|_| rng.below(k + 2)).collect();
+            for (v, color) in colors.iter().enumerate() {
+                text.push_str(&format!("l {} {}\n", v + 1, color));
+            }
+            assert_eq!(graph.read_coloring(text.as_bytes()), Ok(true));
+            assert_eq!(graph.faults().is_empty(), instance.is_coloring(&colors, k));
+        }
+    }
+
+    #[test]
+    fn reports_each_kind() {
+        let mut graph = ::Graph::new();
+        graph.verts(5).edges(3);
+        graph.connect(1, 2);
+        graph.connect(2, 3);
+        graph.connect(3, 3);
+        graph.allow(4, &[2]);
+        let coloring = "c solver output\ns col 3\nl 1 1\nl 2 1\nl 3 2\nl 4 3\nl 5 0\n";
+        assert_eq!(graph.read_coloring(coloring.as_bytes()), Ok(true));
+        let faults: Vec<String> = graph.faults().iter().map(|fault| fault.to_string()).collect();
+        assert_eq!(faults, [
+            Fault::NotAllowed { v: 4, color: 3 }.to_string(),
+            Fault::Uncolored { v: 5 }.to_string(),
+            Fault::Conflict { u: 1, v: 2, color: 1 }.to_string(),
+            Fault::Conflict { u: 3, v: 3, color: 2 }.to_string(),
+        ]);
+    }
+
+    #[test]
+    fn reads_only_the_coloring_format() {
+        let mut graph = ::Graph::new();
+        graph.verts(2);
+        assert_eq!(graph.read_coloring("No Solution.\n".as_bytes()), Ok(false));
+        assert!(graph.read_coloring("s col 2\nl 3 1\n".as_bytes()).is_err());
+        assert!(graph.read_coloring("s col two\n".as_bytes()).is_err());
+        assert!(graph.read_coloring("l 1\n".as_bytes()).is_err());
+    }
+}